@@ -0,0 +1,220 @@
+use crate::memory::phys_to_virt;
+use core::{mem, ptr};
+use x86_64::PhysAddr;
+
+/* O ACPI (Advanced Configuration and Power Interface) descreve o hardware da máquina através de
+* tabelas na memória física. O RSDP (Root System Description Pointer) aponta para a tabela raiz
+* (RSDT ou XSDT), que por sua vez contém o endereço de todas as outras tabelas, como a MADT (que
+* descreve os controladores de interrupção) e a HPET.
+*/
+
+// Cabeçalho comum a todas as tabelas do ACPI (System Description Table).
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+const SDT_HEADER_SIZE: u64 = mem::size_of::<SdtHeader>() as u64;
+
+/* Lê um valor de um endereço físico. Os campos das tabelas ACPI não são alinhados, por isso a leitura
+* é feita com read_unaligned. É unsafe, pois o chamador deve garantir que o endereço é válido.
+*/
+pub(crate) unsafe fn read_phys<T: Copy>(addr: u64) -> T {
+    ptr::read_unaligned(phys_to_virt(PhysAddr::new(addr)).as_ptr::<T>())
+}
+
+// A soma de todos os bytes de uma estrutura ACPI válida deve ser zero (módulo 256).
+fn checksum_ok(addr: u64, length: u64) -> bool {
+    (0..length).fold(0u8, |sum, i| sum.wrapping_add(unsafe { read_phys::<u8>(addr + i) })) == 0
+}
+
+/* O RSDP fica nos primeiros 1 KiB da EBDA (Extended BIOS Data Area), cujo segmento está no endereço
+* 0x40e, ou na área da BIOS entre 0xe0000 e 0xfffff. A assinatura sempre está alinhada em 16 bytes.
+*/
+fn find_rsdp() -> Option<u64> {
+    let ebda = u64::from(unsafe { read_phys::<u16>(0x40e) }) << 4;
+    let regions = [(ebda, ebda + 1024), (0xe0000, 0x100000)];
+
+    regions.iter().filter(|(start, _)| *start != 0).find_map(|&(start, end)| {
+        (start..end).step_by(16).find(|&addr| {
+            let signature: [u8; 8] = unsafe { read_phys(addr) };
+            signature == *b"RSD PTR " && checksum_ok(addr, 20)
+        })
+    })
+}
+
+pub fn table_header(table: PhysAddr) -> SdtHeader {
+    unsafe { read_phys(table.as_u64()) }
+}
+
+/* Procura uma tabela pela assinatura na tabela raiz. A partir da revisão 2 do ACPI o RSDP possui o
+* endereço da XSDT, cujas entradas têm 64 bits em vez dos 32 bits da RSDT.
+*/
+pub fn find_table(signature: &[u8; 4]) -> Option<PhysAddr> {
+    let rsdp = find_rsdp()?;
+    let revision: u8 = unsafe { read_phys(rsdp + 15) };
+    let (root, entry_size) = if revision >= 2 {
+        (unsafe { read_phys::<u64>(rsdp + 24) }, 8)
+    } else {
+        (u64::from(unsafe { read_phys::<u32>(rsdp + 16) }), 4)
+    };
+
+    let root_length = u64::from(table_header(PhysAddr::new(root)).length);
+    let entries = (root_length - SDT_HEADER_SIZE) / entry_size;
+    (0..entries)
+        .map(|i| {
+            let entry = root + SDT_HEADER_SIZE + i * entry_size;
+            if entry_size == 8 {
+                unsafe { read_phys::<u64>(entry) }
+            } else {
+                u64::from(unsafe { read_phys::<u32>(entry) })
+            }
+        })
+        .find(|&table| {
+            let header = table_header(PhysAddr::new(table));
+            header.signature == *signature && checksum_ok(table, u64::from(header.length))
+        })
+        .map(PhysAddr::new)
+}
+
+const FADT_CENTURY: u64 = 108;
+
+pub const MAX_IO_APICS: usize = 4;
+pub const MAX_OVERRIDES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoApicInfo {
+    pub id: u8,
+    pub address: PhysAddr,
+    pub gsi_base: u32,                                                                              // Primeira GSI (Global System Interrupt) atendida por esse I/O APIC
+}
+
+/* Uma entrada de Interrupt Source Override indica que uma IRQ do barramento ISA não está conectada
+* na GSI de mesmo número (por exemplo, o PIT normalmente é ligado na GSI 2) ou que possui polaridade
+* e modo de disparo diferentes do padrão do ISA.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptOverride {
+    pub source: u8,
+    pub gsi: u32,
+    pub flags: u16,
+}
+
+impl InterruptOverride {
+    // Bits 0-1: 00 segue o barramento (ISA é ativo em nível alto), 01 ativo em alto e 11 ativo em baixo.
+    pub fn active_low(&self) -> bool {
+        self.flags & 0b11 == 0b11
+    }
+
+    // Bits 2-3: 00 segue o barramento (ISA é disparado por borda), 01 borda e 11 nível.
+    pub fn level_triggered(&self) -> bool {
+        (self.flags >> 2) & 0b11 == 0b11
+    }
+}
+
+// Informações extraídas da MADT (Multiple APIC Description Table), de assinatura "APIC".
+#[derive(Debug, Clone)]
+pub struct Madt {
+    pub local_apic_address: PhysAddr,
+    pub legacy_pics: bool,                                                                          // A máquina também possui os dois 8259
+    pub io_apics: [Option<IoApicInfo>; MAX_IO_APICS],
+    pub overrides: [Option<InterruptOverride>; MAX_OVERRIDES],
+}
+
+impl Madt {
+    pub fn io_apics(&self) -> impl Iterator<Item = &IoApicInfo> {
+        self.io_apics.iter().flatten()
+    }
+
+    pub fn override_for(&self, irq: u8) -> Option<InterruptOverride> {
+        self.overrides.iter().flatten().find(|entry| entry.source == irq).copied()
+    }
+}
+
+pub fn madt() -> Option<Madt> {
+    let table = find_table(b"APIC")?.as_u64();
+    let length = u64::from(table_header(PhysAddr::new(table)).length);
+
+    let mut madt = Madt {
+        local_apic_address: PhysAddr::new(u64::from(unsafe { read_phys::<u32>(table + 36) })),
+        legacy_pics: unsafe { read_phys::<u32>(table + 40) } & 1 != 0,
+        io_apics: [None; MAX_IO_APICS],
+        overrides: [None; MAX_OVERRIDES],
+    };
+
+    // Após o cabeçalho seguem entradas de tamanho variável no formato (tipo, tamanho, dados).
+    let mut entry = table + 44;
+    while entry + 2 <= table + length {
+        let entry_type: u8 = unsafe { read_phys(entry) };
+        let entry_length: u8 = unsafe { read_phys(entry + 1) };
+        if entry_length < 2 {
+            break;
+        }
+
+        match entry_type {
+            1 => {
+                let io_apic = IoApicInfo {
+                    id: unsafe { read_phys(entry + 2) },
+                    address: PhysAddr::new(u64::from(unsafe { read_phys::<u32>(entry + 4) })),
+                    gsi_base: unsafe { read_phys(entry + 8) },
+                };
+                if let Some(slot) = madt.io_apics.iter_mut().find(|slot| slot.is_none()) {
+                    *slot = Some(io_apic);
+                }
+            }
+            2 => {
+                let interrupt_override = InterruptOverride {
+                    source: unsafe { read_phys(entry + 3) },
+                    gsi: unsafe { read_phys(entry + 4) },
+                    flags: unsafe { read_phys(entry + 8) },
+                };
+                if let Some(slot) = madt.overrides.iter_mut().find(|slot| slot.is_none()) {
+                    *slot = Some(interrupt_override);
+                }
+            }
+            5 => {
+                madt.local_apic_address = PhysAddr::new(unsafe { read_phys(entry + 4) });           // Endereço de 64 bits que substitui o do cabeçalho
+            }
+            _ => {}
+        }
+        entry += u64::from(entry_length);
+    }
+
+    Some(madt)
+}
+
+/* Índice do registrador de século na memória CMOS, informado pelo campo "century" da FADT (tabela com
+* a assinatura "FACP"). Retorna None quando a máquina não possui esse registrador.
+*/
+pub fn century_register() -> Option<u8> {
+    let table = find_table(b"FACP")?.as_u64();
+    if u64::from(table_header(PhysAddr::new(table)).length) <= FADT_CENTURY {
+        return None;
+    }
+    match unsafe { read_phys::<u8>(table + FADT_CENTURY) } {
+        0 => None,
+        register => Some(register),
+    }
+}
+
+#[test_case]
+fn test_century_register() {
+    if let Some(register) = century_register() {
+        assert!((0x0e..0x80).contains(&register));                                                  // Fora dos registradores do relógio e dentro dos 128 bytes do CMOS
+    }
+}
+
+#[test_case]
+fn test_find_madt() {
+    let madt = madt().expect("MADT não encontrada");
+    assert!(madt.io_apics().count() >= 1);
+}
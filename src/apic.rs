@@ -0,0 +1,234 @@
+use crate::acpi::{self, Madt, MAX_IO_APICS};
+use crate::interrupts::{IRQ_COUNT, PIC_1_OFFSET, InterruptIndex};
+use crate::memory::phys_to_virt;
+use core::arch::x86_64::__cpuid;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::PhysAddr;
+use x86_64::registers::model_specific::Msr;
+
+/* O APIC (Advanced Programmable Interrupt Controller) substitui o 8259 nas máquinas modernas. Cada
+* CPU possui um APIC local, que recebe as interrupções e sinaliza o fim delas (EOI), e o I/O APIC
+* recebe as linhas de IRQ dos dispositivos e as redireciona para os APICs locais.
+*
+* O APIC local pode ser acessado por registradores mapeados em memória (modo xAPIC) ou, quando a CPU
+* suporta, por MSRs (modo x2APIC), que dispensa o mapeamento e é mais rápido.
+*/
+
+const IA32_APIC_BASE: u32 = 0x1b;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const APIC_BASE_X2APIC: u64 = 1 << 10;
+const APIC_BASE_ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+const X2APIC_MSR_BASE: u32 = 0x800;
+
+// Deslocamento dos registradores do APIC local no modo xAPIC.
+const REG_ID: u32 = 0x20;
+const REG_TPR: u32 = 0x80;
+const REG_EOI: u32 = 0xb0;
+const REG_SPURIOUS: u32 = 0xf0;
+
+const SPURIOUS_ENABLE: u32 = 1 << 8;
+pub const SPURIOUS_VECTOR: u8 = 0xff;
+
+// Registradores do I/O APIC, acessados escrevendo o índice em IOREGSEL e o valor em IOWIN.
+const IOREGSEL: u64 = 0x00;
+const IOWIN: u64 = 0x10;
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION_TABLE: u32 = 0x10;
+
+const REDIRECTION_ACTIVE_LOW: u32 = 1 << 13;
+const REDIRECTION_LEVEL_TRIGGERED: u32 = 1 << 15;
+const REDIRECTION_MASKED: u32 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApicError {
+    Unsupported,                                                                                    // A CPU não possui APIC local
+    MadtNotFound,
+    NoIoApic,
+}
+
+static X2APIC_MODE: AtomicBool = AtomicBool::new(false);
+static LOCAL_APIC_BASE: AtomicU64 = AtomicU64::new(0);                                             // Endereço virtual dos registradores no modo xAPIC
+
+#[derive(Debug, Clone, Copy)]
+struct IoApic {
+    base: u64,
+    gsi_base: u32,
+    redirection_entries: u32,
+}
+
+// Pino do I/O APIC em que cada IRQ do ISA está conectada.
+#[derive(Debug, Clone, Copy)]
+struct IrqRoute {
+    io_apic: IoApic,
+    pin: u32,
+}
+
+static IRQ_ROUTES: Mutex<[Option<IrqRoute>; IRQ_COUNT]> = Mutex::new([None; IRQ_COUNT]);
+
+// O bit 9 do EDX da CPUID 1 indica a presença do APIC local.
+pub fn is_supported() -> bool {
+    __cpuid(1).edx & (1 << 9) != 0
+}
+
+// O bit 21 do ECX da CPUID 1 indica suporte ao modo x2APIC.
+pub fn x2apic_supported() -> bool {
+    __cpuid(1).ecx & (1 << 21) != 0
+}
+
+pub fn x2apic_enabled() -> bool {
+    X2APIC_MODE.load(Ordering::SeqCst)
+}
+
+fn read_register(register: u32) -> u32 {
+    if x2apic_enabled() {
+        unsafe { Msr::new(X2APIC_MSR_BASE + (register >> 4)).read() as u32 }
+    } else {
+        let address = LOCAL_APIC_BASE.load(Ordering::SeqCst) + u64::from(register);
+        unsafe { ptr::read_volatile(address as *const u32) }
+    }
+}
+
+fn write_register(register: u32, value: u32) {
+    if x2apic_enabled() {
+        unsafe { Msr::new(X2APIC_MSR_BASE + (register >> 4)).write(u64::from(value)) };
+    } else {
+        let address = LOCAL_APIC_BASE.load(Ordering::SeqCst) + u64::from(register);
+        unsafe { ptr::write_volatile(address as *mut u32, value) };
+    }
+}
+
+// No modo xAPIC o identificador fica nos 8 bits mais altos, no x2APIC ocupa o registrador inteiro.
+pub fn id() -> u32 {
+    let id = read_register(REG_ID);
+    if x2apic_enabled() { id } else { id >> 24 }
+}
+
+pub fn end_of_interrupt() {
+    write_register(REG_EOI, 0);
+}
+
+/* Detecta e habilita o APIC local e programa os I/O APICs descritos na MADT. O x2APIC é utilizado
+* quando disponível, a não ser que a opção "nox2apic" esteja presente na linha de comando.
+*/
+pub fn init() -> Result<(), ApicError> {
+    if !is_supported() {
+        return Err(ApicError::Unsupported);
+    }
+    let madt = acpi::madt().ok_or(ApicError::MadtNotFound)?;
+    if madt.io_apics().next().is_none() {
+        return Err(ApicError::NoIoApic);
+    }
+
+    init_local_apic(x2apic_supported() && !crate::cmdline::has_flag("nox2apic"));
+    init_io_apics(&madt);
+    Ok(())
+}
+
+fn init_local_apic(x2apic: bool) {
+    let mut apic_base = Msr::new(IA32_APIC_BASE);
+    let mut value = unsafe { apic_base.read() } | APIC_BASE_ENABLE;
+    if x2apic {
+        value |= APIC_BASE_X2APIC;
+    }
+    unsafe { apic_base.write(value) };
+
+    let base = phys_to_virt(PhysAddr::new(value & APIC_BASE_ADDRESS_MASK));
+    LOCAL_APIC_BASE.store(base.as_u64(), Ordering::SeqCst);
+    X2APIC_MODE.store(x2apic, Ordering::SeqCst);
+
+    write_register(REG_TPR, 0);                                                                     // Aceita interrupções de qualquer prioridade
+    write_register(REG_SPURIOUS, SPURIOUS_ENABLE | u32::from(SPURIOUS_VECTOR));                     // O bit 8 habilita o APIC por software
+}
+
+fn io_apic_read(io_apic: &IoApic, register: u32) -> u32 {
+    unsafe {
+        ptr::write_volatile((io_apic.base + IOREGSEL) as *mut u32, register);
+        ptr::read_volatile((io_apic.base + IOWIN) as *const u32)
+    }
+}
+
+fn io_apic_write(io_apic: &IoApic, register: u32, value: u32) {
+    unsafe {
+        ptr::write_volatile((io_apic.base + IOREGSEL) as *mut u32, register);
+        ptr::write_volatile((io_apic.base + IOWIN) as *mut u32, value);
+    }
+}
+
+/* Cada IRQ do ISA é redirecionada para o vetor 32 + IRQ, o mesmo utilizado com o PIC, assim os
+* manipuladores da IDT não dependem do controlador ativo. As entradas começam mascaradas e são
+* liberadas em set_irq_masked quando um driver registra a linha.
+*/
+fn init_io_apics(madt: &Madt) {
+    let mut io_apics = [None; MAX_IO_APICS];
+    for (slot, info) in io_apics.iter_mut().zip(madt.io_apics()) {
+        let mut io_apic = IoApic {
+            base: phys_to_virt(info.address).as_u64(),
+            gsi_base: info.gsi_base,
+            redirection_entries: 0,
+        };
+        io_apic.redirection_entries = ((io_apic_read(&io_apic, IOAPIC_VERSION) >> 16) & 0xff) + 1;
+        for pin in 0..io_apic.redirection_entries {
+            io_apic_write(&io_apic, IOAPIC_REDIRECTION_TABLE + pin * 2, REDIRECTION_MASKED);
+        }
+        *slot = Some(io_apic);
+    }
+
+    let destination = id();
+    let mut routes = IRQ_ROUTES.lock();
+    for irq in 0..IRQ_COUNT as u8 {
+        if irq == InterruptIndex::Cascade.irq() {
+            continue;
+        }
+
+        /* Sem uma entrada de override a IRQ está ligada na GSI de mesmo número. Se outra IRQ foi
+        * redirecionada para essa GSI (como o PIT, que costuma ocupar a GSI 2), a linha é ignorada.
+        */
+        let interrupt_override = madt.override_for(irq);
+        let gsi = interrupt_override.map_or(u32::from(irq), |entry| entry.gsi);
+        if interrupt_override.is_none() && madt.overrides.iter().flatten().any(|entry| entry.gsi == gsi) {
+            continue;
+        }
+
+        let io_apic = io_apics.iter().flatten().find(|io_apic| {
+            gsi >= io_apic.gsi_base && gsi < io_apic.gsi_base + io_apic.redirection_entries
+        });
+        if let Some(io_apic) = io_apic {
+            let pin = gsi - io_apic.gsi_base;
+            let mut low = u32::from(PIC_1_OFFSET + irq) | REDIRECTION_MASKED;
+            if let Some(entry) = interrupt_override {
+                if entry.active_low() {
+                    low |= REDIRECTION_ACTIVE_LOW;
+                }
+                if entry.level_triggered() {
+                    low |= REDIRECTION_LEVEL_TRIGGERED;
+                }
+            }
+            io_apic_write(io_apic, IOAPIC_REDIRECTION_TABLE + pin * 2 + 1, destination << 24);
+            io_apic_write(io_apic, IOAPIC_REDIRECTION_TABLE + pin * 2, low);
+            routes[usize::from(irq)] = Some(IrqRoute { io_apic: *io_apic, pin });
+        }
+    }
+}
+
+pub fn set_irq_masked(irq: u8, masked: bool) {
+    let routes = IRQ_ROUTES.lock();
+    if let Some(route) = routes.get(usize::from(irq)).copied().flatten() {
+        let register = IOAPIC_REDIRECTION_TABLE + route.pin * 2;
+        let low = io_apic_read(&route.io_apic, register);
+        let low = if masked { low | REDIRECTION_MASKED } else { low & !REDIRECTION_MASKED };
+        io_apic_write(&route.io_apic, register, low);
+    }
+}
+
+#[test_case]
+fn test_local_apic_enabled() {
+    use crate::interrupts::{self, InterruptController};
+
+    if interrupts::controller() == InterruptController::Apic {
+        let apic_base = unsafe { Msr::new(IA32_APIC_BASE).read() };
+        assert!(apic_base & APIC_BASE_ENABLE != 0);
+        assert!(read_register(REG_SPURIOUS) & SPURIOUS_ENABLE != 0);
+    }
+}
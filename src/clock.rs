@@ -0,0 +1,202 @@
+use crate::{hpet, pit};
+use core::arch::x86_64::{__cpuid, __get_cpuid_max, _rdtsc};
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use core::time::Duration;
+
+/* O TSC (Time Stamp Counter) é um contador de 64 bits incrementado pela CPU a cada ciclo e lido com a
+* instrução rdtsc, o que permite medir intervalos com resolução muito menor que a do tick do sistema.
+* A frequência do contador não é informada pela CPU, então ela é calibrada contra um relógio de
+* frequência conhecida (o HPET, quando presente, ou o PIT) durante a inicialização.
+*
+* O Instant só utiliza o TSC quando ele é invariante. Caso contrário ele utiliza o contador principal
+* do HPET ou, sem HPET, o tempo contado pelos ticks do sistema, com a resolução de um tick.
+*/
+static TSC_FREQUENCY_HZ: AtomicU64 = AtomicU64::new(0);
+static SOURCE: AtomicU8 = AtomicU8::new(ClockSource::Ticks as u8);
+static SOURCE_FREQUENCY_HZ: AtomicU64 = AtomicU64::new(NANOS_PER_SECOND);
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ClockSource {
+    Tsc = 0,
+    Hpet = 1,
+    Ticks = 2,
+}
+
+impl ClockSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSource::Tsc => "TSC",
+            ClockSource::Hpet => "HPET",
+            ClockSource::Ticks => "ticks",
+        }
+    }
+
+    // Valor atual do contador da fonte.
+    fn read(self) -> u64 {
+        match self {
+            ClockSource::Tsc => read_tsc(),
+            ClockSource::Hpet => hpet::counter().unwrap_or(0),
+            ClockSource::Ticks => crate::time::uptime().as_nanos() as u64,
+        }
+    }
+}
+
+const CALIBRATION_MS: u64 = 10;
+const CALIBRATION_ROUNDS: usize = 3;
+
+pub fn read_tsc() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+/* Um TSC invariante (bit 8 do EDX da CPUID 0x80000007) mantém a mesma frequência independentemente
+* dos estados de energia e da frequência da CPU. Sem ele as medições podem variar.
+*/
+pub fn is_invariant() -> bool {
+    let (max_extended_leaf, _) = __get_cpuid_max(0x8000_0000);
+    max_extended_leaf >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+/* Calibra o TSC e escolhe a fonte do Instant. Um TSC que não é invariante continua calibrado e
+* disponível por read_tsc, mas não é utilizado para medir intervalos.
+*/
+pub fn init() {
+    TSC_FREQUENCY_HZ.store(calibrate(), Ordering::SeqCst);
+
+    let hpet_frequency = x86_64::instructions::interrupts::without_interrupts(|| hpet::HPET.lock().as_ref().map(hpet::Hpet::frequency));
+    let (source, frequency) = match hpet_frequency {
+        _ if is_invariant() && frequency() != 0 => (ClockSource::Tsc, frequency()),
+        Some(hpet_frequency) => (ClockSource::Hpet, hpet_frequency),
+        None => (ClockSource::Ticks, NANOS_PER_SECOND),
+    };
+    if source != ClockSource::Tsc {
+        crate::println!("TSC nao invariante, utilizando {} como relogio", source.name());
+    }
+    SOURCE_FREQUENCY_HZ.store(frequency, Ordering::SeqCst);
+    SOURCE.store(source as u8, Ordering::SeqCst);
+}
+
+// Fonte utilizada pelo Instant.
+pub fn source() -> ClockSource {
+    match SOURCE.load(Ordering::SeqCst) {
+        0 => ClockSource::Tsc,
+        1 => ClockSource::Hpet,
+        _ => ClockSource::Ticks,
+    }
+}
+
+/* Conta quantos ciclos do TSC ocorrem durante uma espera de duração conhecida. Repetimos a medição e
+* utilizamos o menor valor, já que interrupções do hypervisor só podem aumentar o intervalo medido.
+*/
+fn calibrate() -> u64 {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        match hpet::HPET.lock().as_ref() {
+            Some(hpet) => calibrate_with_hpet(hpet),
+            None => calibrate_with_pit(),
+        }
+    })
+}
+
+fn calibrate_with_pit() -> u64 {
+    let count = (u64::from(pit::BASE_FREQUENCY_HZ) * CALIBRATION_MS / 1000) as u16;
+
+    let cycles = (0..CALIBRATION_ROUNDS)
+        .map(|_| {
+            let start = read_tsc();
+            pit::wait_channel2(count);
+            read_tsc() - start
+        })
+        .min()
+        .unwrap_or(0);
+    cycles * 1000 / CALIBRATION_MS
+}
+
+// O contador principal do HPET tem período conhecido, então basta esperar ele avançar o intervalo.
+fn calibrate_with_hpet(hpet: &hpet::Hpet) -> u64 {
+    let hpet_ticks = hpet.frequency() * CALIBRATION_MS / 1000;
+
+    let (cycles, elapsed) = (0..CALIBRATION_ROUNDS)
+        .map(|_| {
+            let (start_counter, start) = (hpet.counter(), read_tsc());
+            while hpet.counter().wrapping_sub(start_counter) < hpet_ticks {
+                core::hint::spin_loop();
+            }
+            (read_tsc() - start, hpet.counter().wrapping_sub(start_counter))
+        })
+        .min()
+        .unwrap_or((0, 1));
+    (u128::from(cycles) * u128::from(hpet.frequency()) / u128::from(elapsed.max(1))) as u64
+}
+
+pub fn frequency() -> u64 {
+    TSC_FREQUENCY_HZ.load(Ordering::SeqCst)
+}
+
+pub fn is_calibrated() -> bool {
+    frequency() != 0
+}
+
+// Verdadeiro quando o Instant mede com resolução menor que a de um tick.
+pub fn is_precise() -> bool {
+    source() != ClockSource::Ticks
+}
+
+pub fn cycles_to_nanos(cycles: u64) -> u64 {
+    let frequency = frequency();
+    if frequency == 0 {
+        return 0;
+    }
+    (u128::from(cycles) * 1_000_000_000 / u128::from(frequency)) as u64
+}
+
+fn counts_to_nanos(counts: u64) -> u64 {
+    let frequency = SOURCE_FREQUENCY_HZ.load(Ordering::SeqCst);
+    (u128::from(counts) * u128::from(NANOS_PER_SECOND) / u128::from(frequency.max(1))) as u64
+}
+
+// Instante monotônico lido da fonte escolhida em init, semelhante ao std::time::Instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(source().read())
+    }
+
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_nanos(counts_to_nanos(self.0.saturating_sub(earlier.0)))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn elapsed_nanos(&self) -> u64 {
+        counts_to_nanos(source().read().saturating_sub(self.0))
+    }
+}
+
+#[test_case]
+fn test_instant_is_monotonic() {
+    let first = Instant::now();
+    let second = Instant::now();
+    assert!(second >= first);
+}
+
+#[test_case]
+fn test_elapsed_matches_sleep() {
+    let start = Instant::now();
+    crate::time::sleep(10);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(9));
+    assert!(elapsed < Duration::from_millis(500));
+}
+
+#[test_case]
+fn test_source_matches_invariant_tsc() {
+    assert_eq!(source() == ClockSource::Tsc, is_invariant() && is_calibrated());
+    if source() == ClockSource::Hpet {
+        assert!(hpet::is_present());
+    }
+}
@@ -0,0 +1,108 @@
+use spin::Once;
+use x86_64::instructions::port::Port;
+
+/* Opções de inicialização do kernel no formato "chave=valor" ou apenas "flag", separadas por espaço.
+* O bootloader não repassa uma linha de comando para o kernel, então as opções são lidas no boot do
+* arquivo "opt/rust_os/cmdline" do fw_cfg do QEMU, que pode ser trocado a cada execução sem
+* recompilar o kernel, por exemplo:
+*  qemu-system-x86_64 ... -fw_cfg name=opt/rust_os/cmdline,string="interrupts=pic timer=hpet"
+* Quando o arquivo não existe (em hardware real, por exemplo), valem as opções definidas em tempo de
+* compilação através da variável de ambiente RUST_OS_CMDLINE, por exemplo:
+*  RUST_OS_CMDLINE="interrupts=pic" cargo run
+*
+* Opções reconhecidas:
+*  interrupts=pic|apic  controlador de interrupções (padrão: APIC quando disponível)
+*  nox2apic             não habilita o modo x2APIC
+*  timer_hz=N           frequência do tick do sistema em Hz (padrão: 1000)
+*  timer=pit|hpet       timer que gera o tick do sistema (padrão: PIT)
+*  keymap=us|abnt2|uk   layout do teclado (padrão: us)
+*  framebuffer[=LxA]    console no framebuffer do BGA, na resolução LxA (padrão: 1024x768)
+*  scrollback=N         linhas guardadas no histórico da tela (padrão e máximo: 256)
+*  statusbar[=top]      linha de status no final da tela, ou no topo com "top"
+*/
+const BUILD_CMDLINE: &str = match option_env!("RUST_OS_CMDLINE") {
+    Some(cmdline) => cmdline,
+    None => "",
+};
+
+/* O fw_cfg do QEMU é acessado escrevendo a chave de um item na porta 0x510 e lendo o seu conteúdo
+* byte a byte na porta 0x511. O item 0x19 é o diretório de arquivos: um contador big endian seguido de
+* uma entrada de 64 bytes por arquivo, com o tamanho, a chave e o nome do arquivo.
+*/
+const FW_CFG_SELECTOR_PORT: u16 = 0x510;
+const FW_CFG_DATA_PORT: u16 = 0x511;
+const FW_CFG_SIGNATURE: u16 = 0x0000;
+const FW_CFG_FILE_DIR: u16 = 0x0019;
+const FW_CFG_ENTRY_SIZE: usize = 64;
+const FW_CFG_FILE_NAME: &[u8] = b"opt/rust_os/cmdline";
+const MAX_CMDLINE: usize = 256;
+
+struct BootCmdline {
+    bytes: [u8; MAX_CMDLINE],
+    len: usize,
+}
+
+static BOOT_CMDLINE: Once<Option<BootCmdline>> = Once::new();
+
+fn fw_cfg_select(key: u16) {
+    unsafe { Port::new(FW_CFG_SELECTOR_PORT).write(key) };
+}
+
+// Lê os próximos bytes do item selecionado.
+fn fw_cfg_read(buffer: &mut [u8]) {
+    for byte in buffer.iter_mut() {
+        *byte = unsafe { Port::new(FW_CFG_DATA_PORT).read() };
+    }
+}
+
+// Procura o arquivo da linha de comando no diretório do fw_cfg. Retorna None fora do QEMU.
+fn read_fw_cfg() -> Option<BootCmdline> {
+    let mut signature = [0; 4];
+    fw_cfg_select(FW_CFG_SIGNATURE);
+    fw_cfg_read(&mut signature);
+    if &signature != b"QEMU" {
+        return None;
+    }
+
+    let mut count = [0; 4];
+    fw_cfg_select(FW_CFG_FILE_DIR);
+    fw_cfg_read(&mut count);
+    let mut entry = [0; FW_CFG_ENTRY_SIZE];
+    for _ in 0..u32::from_be_bytes(count) {
+        fw_cfg_read(&mut entry);
+        let name = &entry[8..];
+        let name = &name[..name.iter().position(|&byte| byte == 0).unwrap_or(name.len())];
+        if name == FW_CFG_FILE_NAME {
+            let size = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize;
+            let mut cmdline = BootCmdline { bytes: [0; MAX_CMDLINE], len: size.min(MAX_CMDLINE) };
+            fw_cfg_select(u16::from_be_bytes([entry[4], entry[5]]));
+            fw_cfg_read(&mut cmdline.bytes[..cmdline.len]);
+            return Some(cmdline);
+        }
+    }
+    None
+}
+
+// Linha de comando do fw_cfg, lida no primeiro acesso, ou a definida em tempo de compilação.
+pub fn cmdline() -> &'static str {
+    match BOOT_CMDLINE.call_once(read_fw_cfg) {
+        Some(cmdline) => core::str::from_utf8(&cmdline.bytes[..cmdline.len]).unwrap_or("").trim_end_matches('\0'),
+        None => BUILD_CMDLINE,
+    }
+}
+
+// Retorna o valor da opção "chave=valor" ou None caso a opção não esteja presente.
+pub fn get(key: &str) -> Option<&'static str> {
+    cmdline().split_whitespace().find_map(|option| {
+        let mut parts = option.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name == key => Some(value),
+            _ => None,
+        }
+    })
+}
+
+// Verifica se uma opção sem valor (por exemplo "nox2apic") está presente.
+pub fn has_flag(flag: &str) -> bool {
+    cmdline().split_whitespace().any(|option| option == flag)
+}
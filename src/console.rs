@@ -0,0 +1,464 @@
+use crate::keyboard::{self, KeyCode, KeyPress};
+use crate::vga_buffer;
+use crate::{serial, serial_print};
+use core::{fmt, ops::Deref, str};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/* Leitura de linhas com edição, recebendo teclas do teclado PS/2 e bytes da serial (COM1). A mesma
+* edição é exibida no VGA e espelhada na serial, então a linha pode ser digitada em qualquer um dos
+* dois, por exemplo no terminal do host com "qemu -serial stdio".
+*
+* A linha é redesenhada com o caractere de backspace (0x08), que nos terminais e no Writer apenas
+* move o cursor para a esquerda. Como o Writer escreve sempre na última linha da tela, o tamanho da
+* linha é limitado ao espaço restante nessa linha depois do prompt.
+*/
+pub const MAX_LINE: usize = vga_buffer::BUFFER_WIDTH;
+const HISTORY_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKey {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+}
+
+fn key_from_keyboard(key: &KeyPress) -> Option<EditKey> {
+    let edit_key = match (key.event.code, key.character) {
+        (_, Some('\n')) => EditKey::Enter,
+        (_, Some('\u{8}')) => EditKey::Backspace,
+        (_, Some(character)) if !character.is_control() => EditKey::Char(character),
+        (KeyCode::Delete, _) => EditKey::Delete,
+        (KeyCode::ArrowLeft, _) => EditKey::Left,
+        (KeyCode::ArrowRight, _) => EditKey::Right,
+        (KeyCode::Home, _) => EditKey::Home,
+        (KeyCode::End, _) => EditKey::End,
+        (KeyCode::ArrowUp, _) => EditKey::Up,
+        (KeyCode::ArrowDown, _) => EditKey::Down,
+        _ => return None,
+    };
+    Some(edit_key)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SerialState {
+    Normal,
+    Escape,                                                                                         // Recebeu ESC
+    Csi(u8),                                                                                        // Recebeu "ESC [" e o parâmetro numérico até agora
+    Ss3,                                                                                            // Recebeu "ESC O"
+}
+
+/* Decodifica os bytes recebidos do terminal: UTF-8 para os caracteres e as sequências de escape do
+* VT100/xterm para as setas, Home, End e Delete. O Enter pode chegar como "\r", "\n" ou "\r\n".
+*/
+pub struct SerialDecoder {
+    state: SerialState,
+    utf8: [u8; 4],
+    utf8_len: usize,
+    last_was_cr: bool,
+}
+
+impl SerialDecoder {
+    pub const fn new() -> SerialDecoder {
+        SerialDecoder { state: SerialState::Normal, utf8: [0; 4], utf8_len: 0, last_was_cr: false }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<EditKey> {
+        let last_was_cr = core::mem::replace(&mut self.last_was_cr, byte == b'\r');
+        match self.state {
+            SerialState::Escape => {
+                self.state = match byte {
+                    b'[' => SerialState::Csi(0),
+                    b'O' => SerialState::Ss3,
+                    _ => SerialState::Normal,
+                };
+                return None;
+            }
+            SerialState::Csi(parameter) => {
+                if byte.is_ascii_digit() {
+                    self.state = SerialState::Csi(parameter.saturating_mul(10).saturating_add(byte - b'0'));
+                    return None;
+                }
+                self.state = SerialState::Normal;
+                return match (byte, parameter) {
+                    (b'A', _) => Some(EditKey::Up),
+                    (b'B', _) => Some(EditKey::Down),
+                    (b'C', _) => Some(EditKey::Right),
+                    (b'D', _) => Some(EditKey::Left),
+                    (b'H', _) | (b'~', 1) | (b'~', 7) => Some(EditKey::Home),
+                    (b'F', _) | (b'~', 4) | (b'~', 8) => Some(EditKey::End),
+                    (b'~', 3) => Some(EditKey::Delete),
+                    _ => None,
+                };
+            }
+            SerialState::Ss3 => {
+                self.state = SerialState::Normal;
+                return match byte {
+                    b'H' => Some(EditKey::Home),
+                    b'F' => Some(EditKey::End),
+                    _ => None,
+                };
+            }
+            SerialState::Normal => {}
+        }
+
+        match byte {
+            0x1b => {
+                self.state = SerialState::Escape;
+                None
+            }
+            b'\n' if last_was_cr => None,
+            b'\r' | b'\n' => Some(EditKey::Enter),
+            0x08 | 0x7f => Some(EditKey::Backspace),
+            0x20..=0x7e => Some(EditKey::Char(byte as char)),
+            0x80..=0xff => self.feed_utf8(byte),
+            _ => None,
+        }
+    }
+
+    // Acumula os bytes de um caractere UTF-8 de vários bytes até ele estar completo.
+    fn feed_utf8(&mut self, byte: u8) -> Option<EditKey> {
+        if byte & 0xc0 != 0x80 {
+            self.utf8_len = 0;                                                                      // Início de um novo caractere
+        }
+        if self.utf8_len == self.utf8.len() {
+            self.utf8_len = 0;
+            return None;
+        }
+        self.utf8[self.utf8_len] = byte;
+        self.utf8_len += 1;
+
+        match str::from_utf8(&self.utf8[..self.utf8_len]) {
+            Ok(text) => {
+                self.utf8_len = 0;
+                text.chars().next().map(EditKey::Char)
+            }
+            Err(error) if error.error_len().is_some() => {
+                self.utf8_len = 0;
+                None
+            }
+            Err(_) => None,                                                                         // Faltam bytes
+        }
+    }
+}
+
+impl Default for SerialDecoder {
+    fn default() -> Self {
+        SerialDecoder::new()
+    }
+}
+
+// Linha lida pelo read_line, codificada em UTF-8.
+#[derive(Clone, Copy)]
+pub struct Line {
+    bytes: [u8; MAX_LINE * 4],
+    len: usize,
+}
+
+impl Line {
+    fn from_chars(chars: &[char]) -> Line {
+        let mut line = Line { bytes: [0; MAX_LINE * 4], len: 0 };
+        for character in chars {
+            line.len += character.encode_utf8(&mut line.bytes[line.len..]).len();
+        }
+        line
+    }
+
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Deref for Line {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HistoryEntry {
+    chars: [char; MAX_LINE],
+    len: usize,
+}
+
+// Histórico das linhas digitadas, da mais antiga para a mais recente.
+struct History {
+    entries: [HistoryEntry; HISTORY_SIZE],
+    len: usize,
+}
+
+impl History {
+    fn push(&mut self, chars: &[char]) {
+        if chars.is_empty() || self.last() == Some(chars) {
+            return;
+        }
+        if self.len == HISTORY_SIZE {
+            self.entries.rotate_left(1);
+            self.len -= 1;
+        }
+        let entry = &mut self.entries[self.len];
+        entry.chars[..chars.len()].copy_from_slice(chars);
+        entry.len = chars.len();
+        self.len += 1;
+    }
+
+    fn get(&self, index: usize) -> &[char] {
+        let entry = &self.entries[index];
+        &entry.chars[..entry.len]
+    }
+
+    fn last(&self) -> Option<&[char]> {
+        self.len.checked_sub(1).map(|index| self.get(index))
+    }
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    entries: [HistoryEntry { chars: ['\0'; MAX_LINE], len: 0 }; HISTORY_SIZE],
+    len: 0,
+});
+
+static SERIAL_DECODER: Mutex<SerialDecoder> = Mutex::new(SerialDecoder::new());
+
+// Próxima tecla de edição, vinda do teclado ou da serial.
+pub fn next_key() -> Option<EditKey> {
+    while let Some(key) = keyboard::read_key() {
+        if let Some(edit_key) = key_from_keyboard(&key) {
+            return Some(edit_key);
+        }
+    }
+    while let Some(byte) = serial::read_byte() {
+        if let Some(edit_key) = SERIAL_DECODER.lock().feed(byte) {
+            return Some(edit_key);
+        }
+    }
+    None
+}
+
+// Escreve o texto no VGA e na serial.
+pub fn output(text: &str) {
+    crate::print!("{}", text);
+    serial_print!("{}", text);
+}
+
+/* Saída formatada para o VGA e para a serial ao mesmo tempo, utilizada pelo shell para que a resposta
+* apareça no terminal em que o comando foi digitado.
+*/
+#[macro_export]
+macro_rules! console_print {
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! console_println {
+    () => ($crate::console_print!("\n"));
+    ($($arg:tt)*) => ($crate::console_print!("{}\n", format_args!($($arg)*)));
+}
+
+// Adapta o fmt::Write para a função output, sem precisar de um buffer intermediário.
+struct ConsoleWriter;
+
+impl fmt::Write for ConsoleWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        output(s);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    ConsoleWriter.write_fmt(args).unwrap();
+}
+
+fn output_char(character: char) {
+    output(character.encode_utf8(&mut [0; 4]));
+}
+
+fn move_left(count: usize) {
+    (0..count).for_each(|_| output("\u{8}"));
+}
+
+// Estado da linha sendo editada. O cursor de hardware do VGA e o do terminal acompanham a edição.
+struct Editor {
+    chars: [char; MAX_LINE],
+    len: usize,
+    cursor: usize,
+    capacity: usize,
+    history_index: usize,                                                                           // Igual ao tamanho do histórico quando editando uma linha nova
+}
+
+impl Editor {
+    fn new(capacity: usize, history_len: usize) -> Editor {
+        Editor {
+            chars: ['\0'; MAX_LINE],
+            len: 0,
+            cursor: 0,
+            capacity,
+            history_index: history_len,
+        }
+    }
+
+    // Reescreve a linha a partir de "from", apaga o que sobrou da versão anterior e reposiciona o cursor.
+    fn redraw(&mut self, from: usize, old_len: usize) {
+        move_left(self.cursor - from);
+        for index in from..self.len {
+            output_char(self.chars[index]);
+        }
+        let erased = old_len.saturating_sub(self.len);
+        (0..erased).for_each(|_| output(" "));
+        move_left(self.len + erased - from);
+    }
+
+    fn move_to(&mut self, position: usize) {
+        if position < self.cursor {
+            move_left(self.cursor - position);
+        } else {
+            (self.cursor..position).for_each(|index| output_char(self.chars[index]));
+        }
+        self.cursor = position;
+    }
+
+    fn insert(&mut self, character: char) {
+        if self.len == self.capacity {
+            return;
+        }
+        self.chars.copy_within(self.cursor..self.len, self.cursor + 1);
+        self.chars[self.cursor] = character;
+        self.len += 1;
+        let from = self.cursor;
+        self.redraw(from, self.len - 1);
+        self.move_to(from + 1);
+    }
+
+    fn remove(&mut self, position: usize) {
+        self.move_to(position);
+        self.chars.copy_within(position + 1..self.len, position);
+        self.len -= 1;
+        self.redraw(position, self.len + 1);
+    }
+
+    fn replace(&mut self, chars: &[char]) {
+        let old_len = self.len;
+        self.move_to(0);
+        let len = chars.len().min(self.capacity);
+        self.chars[..len].copy_from_slice(&chars[..len]);
+        self.len = len;
+        self.redraw(0, old_len);
+        self.move_to(len);
+    }
+
+    fn recall(&mut self, history: &History, index: usize) {
+        self.history_index = index;
+        if index < history.len {
+            self.replace(history.get(index));
+        } else {
+            self.replace(&[]);
+        }
+    }
+
+    fn apply(&mut self, key: EditKey, history: &History) {
+        match key {
+            EditKey::Char(character) => self.insert(character),
+            EditKey::Backspace if self.cursor > 0 => self.remove(self.cursor - 1),
+            EditKey::Delete if self.cursor < self.len => self.remove(self.cursor),
+            EditKey::Left if self.cursor > 0 => self.move_to(self.cursor - 1),
+            EditKey::Right if self.cursor < self.len => self.move_to(self.cursor + 1),
+            EditKey::Home => self.move_to(0),
+            EditKey::End => self.move_to(self.len),
+            EditKey::Up if self.history_index > 0 => self.recall(history, self.history_index - 1),
+            EditKey::Down if self.history_index < history.len => self.recall(history, self.history_index + 1),
+            _ => {}
+        }
+    }
+}
+
+/* Exibe o prompt e lê uma linha, retornando quando o Enter é pressionado no teclado ou recebido pela
+* serial. O eco automático do teclado fica desabilitado durante a edição.
+*/
+pub fn read_line(prompt: &str) -> Line {
+    output(prompt);
+    let echo = keyboard::set_echo(false);
+
+    let start_column = vga_buffer::column_position();
+    let capacity = (vga_buffer::screen_width() - 1).saturating_sub(start_column).min(MAX_LINE);
+    let history_len = without_interrupts(|| HISTORY.lock().len);
+    let mut editor = Editor::new(capacity, history_len);
+
+    loop {
+        let key = match next_key() {
+            Some(key) => key,
+            None => {
+                crate::status_bar::poll();
+                x86_64::instructions::hlt();
+                continue;
+            }
+        };
+
+        if key == EditKey::Enter {
+            break;
+        }
+        without_interrupts(|| editor.apply(key, &HISTORY.lock()));
+    }
+
+    editor.move_to(editor.len);
+    output("\n");
+    keyboard::set_echo(echo);
+
+    let chars = &editor.chars[..editor.len];
+    without_interrupts(|| HISTORY.lock().push(chars));
+    Line::from_chars(chars)
+}
+
+#[test_case]
+fn test_serial_decoder() {
+    let mut decoder = SerialDecoder::new();
+    let keys: [Option<EditKey>; 9] = [b'a', 0x1b, b'[', b'D', 0x1b, b'[', b'3', b'~', 0x7f].map(|byte| decoder.feed(byte));
+    assert_eq!(keys[0], Some(EditKey::Char('a')));
+    assert_eq!(keys[3], Some(EditKey::Left));
+    assert_eq!(keys[7], Some(EditKey::Delete));
+    assert_eq!(keys[8], Some(EditKey::Backspace));
+
+    assert_eq!(decoder.feed(0xc3), None);
+    assert_eq!(decoder.feed(0xa7), Some(EditKey::Char('ç')));
+    assert_eq!(decoder.feed(b'\r'), Some(EditKey::Enter));
+    assert_eq!(decoder.feed(b'\n'), None);
+}
+
+#[test_case]
+fn test_read_line_editing() {
+    for &byte in b"hhelo\x1b[H\x1b[3~\x1b[C\x1b[Cl\x1b[F!\x7f\r" {
+        serial::push_input(byte);
+    }
+    assert_eq!(read_line("> ").as_str(), "hello");
+}
+
+#[test_case]
+fn test_read_line_history() {
+    for &byte in b"first\rsecond\r\x1b[A\x1b[A\x1b[B!\r" {
+        serial::push_input(byte);
+    }
+    assert_eq!(read_line("> ").as_str(), "first");
+    assert_eq!(read_line("> ").as_str(), "second");
+    assert_eq!(read_line("> ").as_str(), "second!");
+}
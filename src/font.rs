@@ -0,0 +1,261 @@
+use core::convert::TryFrom;
+
+/* Fontes bitmap no formato PSF (PC Screen Font), o mesmo utilizado pelo console do Linux. Cada glifo
+* é uma matriz de bits, uma linha após a outra, com cada linha ocupando um número inteiro de bytes e o
+* bit mais significativo à esquerda. A tabela Unicode opcional no final do arquivo informa quais
+* caracteres cada glifo representa.
+*
+* A fonte padrão foi convertida da fonte misc-fixed 8x13 (domínio público) para células de 8x16, com
+* os caracteres do Latin-1, linhas simples de desenho de caixas e blocos.
+*/
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_START_SEQUENCE: u16 = 0xfffe;
+
+const PSF2_MAGIC: u32 = 0x864a_b572;
+const PSF2_HAS_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnicodeTable {
+    None,
+    Psf1(usize),                                                                                    // Início da tabela no arquivo
+    Psf2(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Font {
+    data: &'static [u8],
+    glyph_offset: usize,
+    glyph_count: usize,
+    bytes_per_glyph: usize,
+    width: usize,
+    height: usize,
+    table: UnicodeTable,
+}
+
+const fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+impl Font {
+    // Interpreta um arquivo PSF1 ou PSF2, retornando None se o cabeçalho for inválido ou o arquivo estiver truncado.
+    pub const fn from_psf(data: &'static [u8]) -> Option<Font> {
+        let font = if data.len() >= 4 && data[0] == PSF1_MAGIC[0] && data[1] == PSF1_MAGIC[1] {
+            let mode = data[2];
+            let height = data[3] as usize;
+            let glyph_count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+            Font {
+                data,
+                glyph_offset: 4,
+                glyph_count,
+                bytes_per_glyph: height,
+                width: 8,
+                height,
+                table: if mode & PSF1_MODE_HAS_TABLE != 0 {
+                    UnicodeTable::Psf1(4 + glyph_count * height)
+                } else {
+                    UnicodeTable::None
+                },
+            }
+        } else if data.len() >= 32 && read_u32(data, 0) == PSF2_MAGIC {
+            let header_size = read_u32(data, 8) as usize;
+            let glyph_count = read_u32(data, 16) as usize;
+            let bytes_per_glyph = read_u32(data, 20) as usize;
+            Font {
+                data,
+                glyph_offset: header_size,
+                glyph_count,
+                bytes_per_glyph,
+                width: read_u32(data, 28) as usize,
+                height: read_u32(data, 24) as usize,
+                table: if read_u32(data, 12) & PSF2_HAS_TABLE != 0 {
+                    UnicodeTable::Psf2(header_size + glyph_count * bytes_per_glyph)
+                } else {
+                    UnicodeTable::None
+                },
+            }
+        } else {
+            return None;
+        };
+
+        if font.glyph_count == 0 || font.width == 0 || font.height == 0 || font.bytes_per_glyph < font.height * font.width.div_ceil(8) {
+            return None;
+        }
+        if data.len() < font.glyph_offset + font.glyph_count * font.bytes_per_glyph {
+            return None;
+        }
+        Some(font)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_count
+    }
+
+    pub fn bytes_per_row(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    // Bitmap do glifo, com bytes_per_row bytes por linha.
+    pub fn glyph(&self, index: usize) -> &'static [u8] {
+        let data = self.data;
+        let start = self.glyph_offset + index.min(self.glyph_count - 1) * self.bytes_per_glyph;
+        &data[start..start + self.bytes_per_glyph]
+    }
+
+    // Verifica se um pixel do glifo está aceso.
+    pub fn pixel(&self, index: usize, x: usize, y: usize) -> bool {
+        let byte = self.glyph(index)[y * self.bytes_per_row() + x / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    /* Procura o glifo de um caractere na tabela Unicode. Sem tabela, o glifo é o próprio código do
+    * caractere. As sequências de caracteres combinados da tabela são ignoradas.
+    */
+    pub fn glyph_index(&self, character: char) -> Option<usize> {
+        match self.table {
+            UnicodeTable::None => Some(character as usize).filter(|&index| index < self.glyph_count),
+            UnicodeTable::Psf1(start) => self.find_psf1(start, character),
+            UnicodeTable::Psf2(start) => self.find_psf2(start, character),
+        }
+    }
+
+    // Primeiro caractere da tabela Unicode associado a um glifo, o inverso de glyph_index.
+    pub fn glyph_character(&self, glyph: usize) -> Option<char> {
+        if glyph >= self.glyph_count {
+            return None;
+        }
+        match self.table {
+            UnicodeTable::None => char::from_u32(glyph as u32),
+            UnicodeTable::Psf1(start) => self.psf1_character(start, glyph),
+            UnicodeTable::Psf2(start) => self.psf2_character(start, glyph),
+        }
+    }
+
+    fn psf1_character(&self, start: usize, target: usize) -> Option<char> {
+        let mut glyph = 0;
+        for entry in self.data[start..].chunks_exact(2) {
+            match u16::from_le_bytes([entry[0], entry[1]]) {
+                PSF1_SEPARATOR => glyph += 1,
+                PSF1_START_SEQUENCE if glyph == target => return None,
+                value if glyph == target => return char::from_u32(u32::from(value)),
+                _ => {}
+            }
+            if glyph > target {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn psf2_character(&self, start: usize, target: usize) -> Option<char> {
+        let mut glyph = 0;
+        let mut position = start;
+        while position < self.data.len() && glyph <= target {
+            match self.data[position] {
+                PSF2_SEPARATOR => glyph += 1,
+                PSF2_START_SEQUENCE if glyph == target => return None,
+                first_byte if glyph == target => {
+                    let end = (position + utf8_length(first_byte)).min(self.data.len());
+                    return core::str::from_utf8(&self.data[position..end]).ok()?.chars().next();
+                }
+                _ => {}
+            }
+            position += 1;
+        }
+        None
+    }
+
+    fn find_psf1(&self, start: usize, character: char) -> Option<usize> {
+        let code = u16::try_from(u32::from(character)).ok()?;
+        let mut glyph = 0;
+        let mut in_sequence = false;
+        for entry in self.data[start..].chunks_exact(2) {
+            match u16::from_le_bytes([entry[0], entry[1]]) {
+                PSF1_SEPARATOR => {
+                    glyph += 1;
+                    in_sequence = false;
+                }
+                PSF1_START_SEQUENCE => in_sequence = true,
+                value if value == code && !in_sequence => return Some(glyph),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn find_psf2(&self, start: usize, character: char) -> Option<usize> {
+        let mut encoded = [0; 4];
+        let encoded = character.encode_utf8(&mut encoded).as_bytes();
+        let mut glyph = 0;
+        let mut in_sequence = false;
+        let mut position = start;
+        while position < self.data.len() {
+            match self.data[position] {
+                PSF2_SEPARATOR => {
+                    glyph += 1;
+                    in_sequence = false;
+                    position += 1;
+                }
+                PSF2_START_SEQUENCE => {
+                    in_sequence = true;
+                    position += 1;
+                }
+                _ => {
+                    if !in_sequence && self.data[position..].starts_with(encoded) {
+                        return Some(glyph);
+                    }
+                    position += utf8_length(self.data[position]);
+                }
+            }
+        }
+        None
+    }
+
+    // Glifo exibido no lugar dos caracteres que não existem na fonte.
+    pub fn fallback_glyph(&self) -> usize {
+        self.glyph_index('\u{fffd}').or_else(|| self.glyph_index('?')).unwrap_or(0)
+    }
+}
+
+fn utf8_length(first_byte: u8) -> usize {
+    match first_byte {
+        0xf0..=0xf7 => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    }
+}
+
+pub static DEFAULT_FONT: Font = match Font::from_psf(include_bytes!("../fonts/latin1_8x16.psf")) {
+    Some(font) => font,
+    None => panic!("fonte PSF padrao invalida"),
+};
+
+#[test_case]
+fn test_default_font() {
+    assert_eq!((DEFAULT_FONT.width(), DEFAULT_FONT.height()), (8, 16));
+    assert_eq!(DEFAULT_FONT.glyph_index('A'), Some(0x41));
+    assert_eq!(DEFAULT_FONT.glyph_index('é'), Some(0xe9));
+    assert_eq!(DEFAULT_FONT.glyph_index('─'), Some(0x80));
+    assert_eq!(DEFAULT_FONT.glyph_index('€'), None);
+    assert_eq!(DEFAULT_FONT.glyph_character(0xe9), Some('é'));
+    assert_eq!(DEFAULT_FONT.glyph_character(0x80), Some('─'));
+    assert_eq!(DEFAULT_FONT.fallback_glyph(), 0x7f);
+
+    let lit = (0..16).flat_map(|y| (0..8).map(move |x| (x, y))).filter(|&(x, y)| DEFAULT_FONT.pixel(0x41, x, y)).count();
+    assert!(lit > 0);
+    let block = DEFAULT_FONT.glyph_index('█').unwrap();
+    assert!((0..8).all(|x| DEFAULT_FONT.pixel(block, x, 0)));
+}
@@ -0,0 +1,340 @@
+use crate::font::{Font, DEFAULT_FONT};
+use crate::memory::phys_to_virt;
+use crate::vga_buffer::Color;
+use crate::{cmdline, pci};
+use core::convert::TryFrom;
+use core::{fmt, ptr};
+use spin::Mutex;
+use x86_64::PhysAddr;
+use x86_64::instructions::port::Port;
+
+/* O adaptador gráfico do Bochs e do QEMU (BGA, Bochs Graphics Adapter, "-vga std") é programado por
+* registradores de 16 bits acessados escrevendo o índice na porta 0x1ce e o valor na porta 0x1cf. Com
+* o linear framebuffer habilitado, a imagem fica em uma região contínua de memória cujo endereço é o
+* BAR 0 do dispositivo PCI 1234:1111. O bootloader não configura um modo VBE, então o modo é definido
+* diretamente pelo kernel.
+*/
+const BGA_INDEX_PORT: u16 = 0x1ce;
+const BGA_DATA_PORT: u16 = 0x1cf;
+
+const BGA_REG_ID: u16 = 0;
+const BGA_REG_XRES: u16 = 1;
+const BGA_REG_YRES: u16 = 2;
+const BGA_REG_BPP: u16 = 3;
+const BGA_REG_ENABLE: u16 = 4;
+const BGA_REG_VIRT_WIDTH: u16 = 6;
+
+const BGA_ID_MIN: u16 = 0xb0c0;
+const BGA_ENABLED: u16 = 0x01;
+const BGA_LFB_ENABLED: u16 = 0x40;
+
+const BGA_PCI_VENDOR: u16 = 0x1234;
+const BGA_PCI_DEVICE: u16 = 0x1111;
+const BGA_DEFAULT_LFB: u64 = 0xe000_0000;                                                           // Endereço fixo do Bochs quando o adaptador não está no PCI
+const PCI_BAR_ADDRESS_MASK: u32 = !0xf;
+
+const BITS_PER_PIXEL: u16 = 32;
+const DEFAULT_WIDTH: usize = 1024;
+const DEFAULT_HEIGHT: usize = 768;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramebufferError {
+    NoDevice,
+    UnsupportedMode,
+}
+
+fn bga_write(index: u16, value: u16) {
+    unsafe {
+        Port::new(BGA_INDEX_PORT).write(index);
+        Port::new(BGA_DATA_PORT).write(value);
+    }
+}
+
+fn bga_read(index: u16) -> u16 {
+    unsafe {
+        Port::new(BGA_INDEX_PORT).write(index);
+        Port::new(BGA_DATA_PORT).read()
+    }
+}
+
+// Memória de vídeo com pixels de 32 bits no formato 0x00RRGGBB.
+#[derive(Debug, Clone, Copy)]
+pub struct Framebuffer {
+    base: *mut u32,
+    width: usize,
+    height: usize,
+    stride: usize,                                                                                  // Pixels por linha na memória, que pode ser maior que a largura visível
+}
+
+unsafe impl Send for Framebuffer {}
+
+impl Framebuffer {
+    /* Cria um framebuffer sobre uma região de memória. O chamador deve garantir que a região possui
+    * stride * height pixels e que ela não é utilizada de outra forma.
+    */
+    pub(crate) unsafe fn new(base: *mut u32, width: usize, height: usize, stride: usize) -> Framebuffer {
+        Framebuffer { base, width, height, stride }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn put_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            unsafe { ptr::write_volatile(self.base.add(y * self.stride + x), color) };
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(unsafe { ptr::read_volatile(self.base.add(y * self.stride + x)) })
+        } else {
+            None
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                self.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    // Copia uma sequência de pixels para a linha y a partir da coluna x, descartando o que passar da borda.
+    pub fn write_span(&mut self, x: usize, y: usize, pixels: &[u32]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let len = pixels.len().min(self.width - x);
+        unsafe { ptr::copy_nonoverlapping(pixels.as_ptr(), self.base.add(y * self.stride + x), len) };
+    }
+
+    // Sobe a imagem em lines linhas de pixels, preenchendo as linhas que surgem no final com a cor.
+    pub fn scroll_up(&mut self, lines: usize, color: u32) {
+        let lines = lines.min(self.height);
+        unsafe {
+            ptr::copy(self.base.add(lines * self.stride), self.base, (self.height - lines) * self.stride);
+        }
+        self.fill_rect(0, self.height - lines, self.width, lines, color);
+    }
+}
+
+/* Programa o BGA com a resolução pedida e 32 bits por pixel. O adaptador ajusta resoluções que não
+* suporta, então os registradores são lidos de volta para confirmar o modo.
+*/
+pub fn set_mode(width: usize, height: usize) -> Result<Framebuffer, FramebufferError> {
+    if bga_read(BGA_REG_ID) < BGA_ID_MIN {
+        return Err(FramebufferError::NoDevice);
+    }
+    let (xres, yres) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(xres), Ok(yres)) if xres > 0 && yres > 0 => (xres, yres),
+        _ => return Err(FramebufferError::UnsupportedMode),
+    };
+
+    bga_write(BGA_REG_ENABLE, 0);
+    bga_write(BGA_REG_XRES, xres);
+    bga_write(BGA_REG_YRES, yres);
+    bga_write(BGA_REG_BPP, BITS_PER_PIXEL);
+    bga_write(BGA_REG_ENABLE, BGA_ENABLED | BGA_LFB_ENABLED);
+    if bga_read(BGA_REG_XRES) != xres || bga_read(BGA_REG_YRES) != yres || bga_read(BGA_REG_BPP) != BITS_PER_PIXEL {
+        bga_write(BGA_REG_ENABLE, 0);
+        return Err(FramebufferError::UnsupportedMode);
+    }
+
+    let address = pci::find_device(BGA_PCI_VENDOR, BGA_PCI_DEVICE)
+        .map_or(BGA_DEFAULT_LFB, |device| u64::from(device.bar(0) & PCI_BAR_ADDRESS_MASK));
+    let base = phys_to_virt(PhysAddr::new(address)).as_mut_ptr();
+    let stride = usize::from(bga_read(BGA_REG_VIRT_WIDTH)).max(width);
+    Ok(unsafe { Framebuffer::new(base, width, height, stride) })
+}
+
+// Desliga o BGA, voltando para o modo de texto do VGA.
+pub fn disable() {
+    bga_write(BGA_REG_ENABLE, 0);
+    *CONSOLE.lock() = None;
+}
+
+// Paleta padrão do VGA, utilizada para que as cores do modo texto tenham a mesma aparência no framebuffer.
+pub const PALETTE: [u32; 16] = [
+    0x000000, 0x0000aa, 0x00aa00, 0x00aaaa, 0xaa0000, 0xaa00aa, 0xaa5500, 0xaaaaaa,
+    0x555555, 0x5555ff, 0x55ff55, 0x55ffff, 0xff5555, 0xff55ff, 0xffff55, 0xffffff,
+];
+
+pub fn rgb(color: Color) -> u32 {
+    PALETTE[color as usize]
+}
+
+const TAB_WIDTH: usize = 8;
+
+/* Console de texto desenhado no framebuffer com uma fonte bitmap. Possui a mesma interface de escrita
+* do vga_buffer::Writer, com a quantidade de linhas e colunas definida pela resolução e pela fonte.
+*/
+pub struct FramebufferWriter {
+    framebuffer: Framebuffer,
+    font: &'static Font,
+    columns: usize,
+    rows: usize,
+    column_position: usize,
+    row_position: usize,
+    foreground: u32,
+    background: u32,
+}
+
+impl FramebufferWriter {
+    pub fn new(framebuffer: Framebuffer, font: &'static Font) -> FramebufferWriter {
+        let mut writer = FramebufferWriter {
+            framebuffer,
+            font,
+            columns: framebuffer.width() / font.width(),
+            rows: framebuffer.height() / font.height(),
+            column_position: 0,
+            row_position: 0,
+            foreground: rgb(Color::Yellow),
+            background: rgb(Color::Black),
+        };
+        writer.clear_screen();
+        writer
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        (self.row_position, self.column_position)
+    }
+
+    pub fn column_position(&self) -> usize {
+        self.column_position
+    }
+
+    pub fn set_color(&mut self, foreground: Color, background: Color) {
+        self.set_rgb(rgb(foreground), rgb(background));
+    }
+
+    // Diferente do modo texto, o framebuffer aceita qualquer cor no formato 0x00RRGGBB.
+    pub fn set_rgb(&mut self, foreground: u32, background: u32) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    pub fn clear_screen(&mut self) {
+        let (width, height) = (self.framebuffer.width(), self.framebuffer.height());
+        self.framebuffer.fill_rect(0, 0, width, height, self.background);
+        self.column_position = 0;
+        self.row_position = 0;
+    }
+
+    pub fn write_string(&mut self, s: &str) {
+        s.chars().for_each(|character| self.write_char(character));
+    }
+
+    pub fn write_char(&mut self, character: char) {
+        match character {
+            '\n' => self.new_line(),
+            '\r' => self.column_position = 0,
+            '\t' => {
+                let next_stop = (self.column_position / TAB_WIDTH + 1) * TAB_WIDTH;
+                while self.column_position < next_stop.min(self.columns) {
+                    self.write_char(' ');
+                }
+            }
+            '\u{8}' => self.column_position = self.column_position.saturating_sub(1),
+            character => {
+                if self.column_position >= self.columns {
+                    self.new_line();
+                }
+                let glyph = self.font.glyph_index(character).unwrap_or_else(|| self.font.fallback_glyph());
+                self.draw_glyph(self.row_position, self.column_position, glyph);
+                self.column_position += 1;
+            }
+        }
+    }
+
+    // Desenha o glifo na célula, pintando também o fundo para apagar o caractere anterior.
+    fn draw_glyph(&mut self, row: usize, col: usize, glyph: usize) {
+        let (left, top) = (col * self.font.width(), row * self.font.height());
+        for y in 0..self.font.height() {
+            for x in 0..self.font.width() {
+                let color = if self.font.pixel(glyph, x, y) { self.foreground } else { self.background };
+                self.framebuffer.put_pixel(left + x, top + y, color);
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        if self.row_position + 1 < self.rows {
+            self.row_position += 1;
+        } else {
+            self.framebuffer.scroll_up(self.font.height(), self.background);
+        }
+        self.column_position = 0;
+    }
+}
+
+impl fmt::Write for FramebufferWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
+
+/* Console do framebuffer, quando habilitado. As macros print e println escrevem nele no lugar do
+* buffer de texto do VGA.
+*/
+pub static CONSOLE: Mutex<Option<FramebufferWriter>> = Mutex::new(None);
+
+pub fn is_enabled() -> bool {
+    x86_64::instructions::interrupts::without_interrupts(|| CONSOLE.lock().is_some())
+}
+
+// Lê a resolução no formato "LARGURAxALTURA", por exemplo "800x600".
+fn parse_resolution(value: &str) -> Option<(usize, usize)> {
+    let (width, height) = value.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/* Habilita o console do framebuffer quando a opção "framebuffer" está na linha de comando, seja como
+* flag (resolução padrão de 1024x768) ou com a resolução desejada. Sem a opção o console continua no
+* modo texto.
+*/
+pub fn init() -> Result<(), FramebufferError> {
+    let (width, height) = match cmdline::get("framebuffer") {
+        Some(value) => parse_resolution(value).ok_or(FramebufferError::UnsupportedMode)?,
+        None if cmdline::has_flag("framebuffer") => (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+        None => return Ok(()),
+    };
+    let framebuffer = set_mode(width, height)?;
+    let writer = FramebufferWriter::new(framebuffer, &DEFAULT_FONT);
+    x86_64::instructions::interrupts::without_interrupts(|| *CONSOLE.lock() = Some(writer));
+    Ok(())
+}
+
+#[test_case]
+fn test_framebuffer_writer() {
+    static mut PIXELS: [u32; 32 * 40] = [0; 32 * 40];
+
+    let framebuffer = unsafe { Framebuffer::new(ptr::addr_of_mut!(PIXELS).cast(), 32, 40, 32) };
+    let mut writer = FramebufferWriter::new(framebuffer, &DEFAULT_FONT);
+    assert_eq!((writer.columns(), writer.rows()), (4, 2));
+
+    writer.write_string("█\t");
+    assert_eq!(writer.position(), (0, 4));
+    assert_eq!(framebuffer.pixel(0, 0), Some(rgb(Color::Yellow)));
+    assert_eq!(framebuffer.pixel(8, 0), Some(rgb(Color::Black)));
+
+    writer.write_string("x\ny");                                                                    // O "x" vai para a segunda linha e a quebra de linha rola a tela
+    assert_eq!(writer.position(), (1, 1));
+    assert_eq!(framebuffer.pixel(0, 0), Some(rgb(Color::Black)));
+    assert_eq!(framebuffer.pixel(0, 39), Some(rgb(Color::Black)));
+}
@@ -0,0 +1,61 @@
+use x86_64::VirtAddr;
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::structures::gdt::{GlobalDescriptorTable, Descriptor, SegmentSelector};
+use lazy_static::lazy_static;
+
+/* Índice da Interrupt Stack Table (IST) utilizada pelo manipulador de double fault. A IST faz parte
+* da TSS (Task State Segment) e contém até 7 ponteiros para pilhas conhecidas e válidas. Quando uma
+* entrada da IDT aponta para um índice da IST, a CPU troca para essa pilha antes de chamar o
+* manipulador, mesmo que a pilha atual esteja corrompida (por exemplo, em um estouro de pilha).
+*/
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+lazy_static! {
+    static ref TSS: TaskStateSegment = {
+        let mut tss = TaskStateSegment::new();
+        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
+            /* Ainda não temos gerenciamento de memória, logo utilizamos um array estático como
+            * pilha. O static mut é necessário, pois um static imutável seria mapeado como somente
+            * leitura pelo bootloader. As pilhas no x86 crescem para baixo, então o endereço
+            * armazenado é o final da área reservada.
+            */
+            const STACK_SIZE: usize = 4096 * 5;
+            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+
+            let stack_start = VirtAddr::from_ptr(core::ptr::addr_of!(STACK));
+            stack_start + STACK_SIZE                                                                // Topo da pilha
+        };
+        tss
+    };
+}
+
+/* A GDT (Global Descriptor Table) é uma relíquia da segmentação de memória, mas ainda é necessária
+* no modo 64 bits para carregar a TSS e configurar o segmento de código do kernel.
+*/
+lazy_static! {
+    static ref GDT: (GlobalDescriptorTable, Selectors) = {
+        let mut gdt = GlobalDescriptorTable::new();
+        let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        (gdt, Selectors { code_selector, tss_selector })
+    };
+}
+
+struct Selectors {
+    code_selector: SegmentSelector,
+    tss_selector: SegmentSelector,
+}
+
+pub fn init() {
+    use x86_64::instructions::tables::load_tss;
+    use x86_64::instructions::segmentation::{CS, Segment};
+
+    GDT.0.load();
+    unsafe {
+        /* Após carregar a nova GDT o registrador CS ainda aponta para o segmento antigo, então é
+        * necessário recarregá-lo e informar à CPU qual TSS utilizar.
+        */
+        CS::set_reg(GDT.1.code_selector);
+        load_tss(GDT.1.tss_selector);
+    }
+}
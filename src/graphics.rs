@@ -0,0 +1,448 @@
+use crate::framebuffer::Framebuffer;
+
+/* Primitivas de desenho 2D sobre qualquer superfície de pixels de 32 bits (o framebuffer ou um buffer
+* em memória). As cores opacas usam o formato 0x00RRGGBB do framebuffer e as imagens com transparência
+* usam 0xAARRGGBB, em que o canal alfa 0 é transparente e 255 é opaco.
+*/
+pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    (a as u32) << 24 | rgb(r, g, b)
+}
+
+// Mistura a cor src (0xAARRGGBB) sobre a cor opaca dst de acordo com o alfa de src.
+pub fn blend(dst: u32, src: u32) -> u32 {
+    let alpha = src >> 24;
+    match alpha {
+        0 => dst,
+        255 => src & 0x00ff_ffff,
+        _ => {
+            let channel = |shift: u32| {
+                let (s, d) = ((src >> shift) & 0xff, (dst >> shift) & 0xff);
+                ((s * alpha + d * (255 - alpha) + 127) / 255) << shift
+            };
+            channel(16) | channel(8) | channel(0)
+        }
+    }
+}
+
+// Retângulo com coordenadas com sinal, para que as formas possam começar fora da tela.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let (right, bottom) = (self.right().min(other.right()), self.bottom().min(other.bottom()));
+        Rect::new(x, y, (right - x).max(0), (bottom - y).max(0))
+    }
+
+    // Menor retângulo que contém os dois.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+}
+
+/* Superfície em que as primitivas desenham. Os pixels recebidos já estão dentro dos limites, e as
+* superfícies com buffer duplo são avisadas da área alterada por mark_dirty.
+*/
+pub trait Canvas {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32);
+    fn get_pixel(&self, x: usize, y: usize) -> u32;
+
+    fn mark_dirty(&mut self, _area: Rect) {}
+
+    fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width() as i32, self.height() as i32)
+    }
+}
+
+impl Canvas for Framebuffer {
+    fn width(&self) -> usize {
+        Framebuffer::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Framebuffer::height(self)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        self.put_pixel(x, y, color);
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixel(x, y).unwrap_or(0)
+    }
+}
+
+// Imagem com pixels no formato 0xAARRGGBB, uma linha após a outra.
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: &'a [u32],
+}
+
+/* Desenha na superfície respeitando um retângulo de recorte (clipping): os pixels fora dele são
+* descartados, o que permite desenhar em uma parte da tela sem afetar o resto.
+*/
+pub struct Painter<'a, C: Canvas> {
+    canvas: &'a mut C,
+    clip: Rect,
+}
+
+impl<'a, C: Canvas> Painter<'a, C> {
+    pub fn new(canvas: &'a mut C) -> Painter<'a, C> {
+        let clip = canvas.bounds();
+        Painter { canvas, clip }
+    }
+
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    // O recorte é sempre limitado à superfície.
+    pub fn set_clip(&mut self, clip: Rect) {
+        self.clip = clip.intersection(&self.canvas.bounds());
+    }
+
+    pub fn reset_clip(&mut self) {
+        self.clip = self.canvas.bounds();
+    }
+
+    fn mark(&mut self, area: Rect) {
+        let area = area.intersection(&self.clip);
+        if !area.is_empty() {
+            self.canvas.mark_dirty(area);
+        }
+    }
+
+    fn plot(&mut self, x: i32, y: i32, color: u32) {
+        if self.clip.contains(x, y) {
+            self.canvas.set_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    pub fn draw_pixel(&mut self, x: i32, y: i32, color: u32) {
+        self.mark(Rect::new(x, y, 1, 1));
+        self.plot(x, y, color);
+    }
+
+    // Algoritmo de Bresenham, que utiliza apenas aritmética inteira.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+        self.mark(Rect::new(x0.min(x1), y0.min(y1), (x1 - x0).abs() + 1, (y1 - y0).abs() + 1));
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    pub fn draw_rect(&mut self, rect: Rect, color: u32) {
+        if rect.is_empty() {
+            return;
+        }
+        let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+        self.draw_line(rect.x, rect.y, right, rect.y, color);
+        self.draw_line(rect.x, bottom, right, bottom, color);
+        self.draw_line(rect.x, rect.y, rect.x, bottom, color);
+        self.draw_line(right, rect.y, right, bottom, color);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: u32) {
+        let area = rect.intersection(&self.clip);
+        self.mark(area);
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                self.canvas.set_pixel(x as usize, y as usize, color);
+            }
+        }
+    }
+
+    // Algoritmo do ponto médio, desenhando os oito octantes a partir de um deles.
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32) {
+        if radius < 0 {
+            return;
+        }
+        self.mark(Rect::new(cx - radius, cy - radius, 2 * radius + 1, 2 * radius + 1));
+        let (mut x, mut y, mut error) = (radius, 0, 1 - radius);
+        while x >= y {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.plot(cx + px, cy + py, color);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32) {
+        if radius < 0 {
+            return;
+        }
+        self.mark(Rect::new(cx - radius, cy - radius, 2 * radius + 1, 2 * radius + 1));
+        for dy in -radius..=radius {
+            let mut dx = 0;
+            while (dx + 1) * (dx + 1) + dy * dy <= radius * radius {
+                dx += 1;
+            }
+            self.fill_span(cx - dx, cx + dx, cy + dy, color);
+        }
+    }
+
+    // Preenche o triângulo testando, para cada pixel do retângulo que o envolve, o lado de cada aresta.
+    pub fn fill_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: u32) {
+        let edge = |p: (i32, i32), q: (i32, i32), x: i32, y: i32| (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0);
+        let area = edge(a, b, c.0, c.1);
+        if area == 0 {
+            return;
+        }
+        let (left, top) = (a.0.min(b.0).min(c.0), a.1.min(b.1).min(c.1));
+        let (right, bottom) = (a.0.max(b.0).max(c.0), a.1.max(b.1).max(c.1));
+        let bounds = Rect::new(left, top, right - left + 1, bottom - top + 1).intersection(&self.clip);
+        self.mark(bounds);
+        for y in bounds.y..bounds.bottom() {
+            for x in bounds.x..bounds.right() {
+                let weights = [edge(b, c, x, y), edge(c, a, x, y), edge(a, b, x, y)];
+                if weights.iter().all(|&w| w == 0 || (w > 0) == (area > 0)) {
+                    self.canvas.set_pixel(x as usize, y as usize, color);
+                }
+            }
+        }
+    }
+
+    fn fill_span(&mut self, x0: i32, x1: i32, y: i32, color: u32) {
+        if y < self.clip.y || y >= self.clip.bottom() {
+            return;
+        }
+        for x in x0.max(self.clip.x)..=x1.min(self.clip.right() - 1) {
+            self.canvas.set_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    // Copia a imagem com o canto superior esquerdo em (x, y), misturando os pixels semitransparentes.
+    pub fn blit(&mut self, image: &Image, x: i32, y: i32) {
+        let area = Rect::new(x, y, image.width as i32, image.height as i32).intersection(&self.clip);
+        self.mark(area);
+        for py in area.y..area.bottom() {
+            for px in area.x..area.right() {
+                let source = image.pixels[(py - y) as usize * image.width + (px - x) as usize];
+                let (cx, cy) = (px as usize, py as usize);
+                let color = blend(self.canvas.get_pixel(cx, cy), source);
+                self.canvas.set_pixel(cx, cy, color);
+            }
+        }
+    }
+}
+
+/* Buffer duplo: o desenho é feito em um buffer em memória e apenas as áreas alteradas (retângulos
+* sujos) são copiadas para o framebuffer em flush, evitando que a tela mostre um quadro pela metade.
+* Como não há alocação dinâmica, a memória do buffer é fornecida pelo chamador.
+*/
+const MAX_DIRTY_RECTS: usize = 8;
+
+pub struct DoubleBuffer<'a> {
+    front: Framebuffer,
+    back: &'a mut [u32],
+    dirty: [Rect; MAX_DIRTY_RECTS],
+    dirty_count: usize,
+}
+
+impl<'a> DoubleBuffer<'a> {
+    // Retorna None se o buffer não tiver um pixel para cada pixel do framebuffer.
+    pub fn new(front: Framebuffer, back: &'a mut [u32]) -> Option<DoubleBuffer<'a>> {
+        if back.len() < front.width() * front.height() {
+            return None;
+        }
+        Some(DoubleBuffer { front, back, dirty: [Rect::new(0, 0, 0, 0); MAX_DIRTY_RECTS], dirty_count: 0 })
+    }
+
+    pub fn dirty_rects(&self) -> &[Rect] {
+        &self.dirty[..self.dirty_count]
+    }
+
+    // Copia as áreas alteradas para o framebuffer.
+    pub fn flush(&mut self) {
+        let width = self.front.width();
+        for rect in self.dirty[..self.dirty_count].iter() {
+            for y in rect.y as usize..rect.bottom() as usize {
+                let start = y * width + rect.x as usize;
+                self.front.write_span(rect.x as usize, y, &self.back[start..start + rect.width as usize]);
+            }
+        }
+        self.dirty_count = 0;
+    }
+
+    // Copia o buffer inteiro, utilizado para o primeiro quadro.
+    pub fn flush_all(&mut self) {
+        self.dirty_count = 0;
+        self.mark_dirty(self.bounds());
+        self.flush();
+    }
+}
+
+impl Canvas for DoubleBuffer<'_> {
+    fn width(&self) -> usize {
+        self.front.width()
+    }
+
+    fn height(&self) -> usize {
+        self.front.height()
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        let width = self.front.width();
+        self.back[y * width + x] = color;
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        self.back[y * self.front.width() + x]
+    }
+
+    /* Junta a área a um retângulo sujo que a intercepte. Quando a lista está cheia, a área é unida ao
+    * retângulo que menos cresce com ela.
+    */
+    fn mark_dirty(&mut self, area: Rect) {
+        let area = area.intersection(&self.bounds());
+        if area.is_empty() {
+            return;
+        }
+        let dirty = &mut self.dirty[..self.dirty_count];
+        if let Some(rect) = dirty.iter_mut().find(|rect| !rect.intersection(&area).is_empty()) {
+            *rect = rect.union(&area);
+        } else if self.dirty_count < MAX_DIRTY_RECTS {
+            self.dirty[self.dirty_count] = area;
+            self.dirty_count += 1;
+        } else {
+            let growth = |rect: &Rect| {
+                let union = rect.union(&area);
+                union.width * union.height - rect.width * rect.height
+            };
+            let rect = self.dirty.iter_mut().min_by_key(|rect| growth(rect)).unwrap();
+            *rect = rect.union(&area);
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_framebuffer(pixels: &mut [u32], width: usize, height: usize) -> Framebuffer {
+    unsafe { Framebuffer::new(pixels.as_mut_ptr(), width, height, width) }
+}
+
+#[test_case]
+fn test_lines_and_clipping() {
+    let mut pixels = [0u32; 16 * 16];
+    let mut framebuffer = test_framebuffer(&mut pixels, 16, 16);
+    let mut painter = Painter::new(&mut framebuffer);
+
+    painter.draw_line(0, 0, 15, 15, 0xff);
+    painter.set_clip(Rect::new(0, 0, 8, 16));
+    painter.draw_line(0, 3, 40, 3, 0xee);
+    painter.fill_rect(Rect::new(-4, 10, 100, 2), 0xdd);
+
+    assert!((0..16).all(|i| framebuffer.get_pixel(i, i) == 0xff || i == 3 || i == 10 || i == 11));
+    assert_eq!((framebuffer.get_pixel(7, 3), framebuffer.get_pixel(8, 3)), (0xee, 0));
+    assert_eq!((framebuffer.get_pixel(0, 10), framebuffer.get_pixel(8, 11)), (0xdd, 0));
+}
+
+#[test_case]
+fn test_circles_and_triangles() {
+    let mut pixels = [0u32; 16 * 16];
+    let mut framebuffer = test_framebuffer(&mut pixels, 16, 16);
+    let mut painter = Painter::new(&mut framebuffer);
+
+    painter.draw_circle(8, 8, 5, 1);
+    assert!([(13, 8), (3, 8), (8, 13), (8, 3)].iter().all(|&(x, y)| framebuffer.get_pixel(x, y) == 1));
+    assert_eq!(framebuffer.get_pixel(8, 8), 0);
+
+    let mut painter = Painter::new(&mut framebuffer);
+    painter.fill_circle(8, 8, 3, 2);
+    painter.fill_triangle((0, 0), (4, 0), (0, 4), 3);
+    let count = |color| (0..16 * 16).filter(|&i| framebuffer.get_pixel(i % 16, i / 16) == color).count();
+    assert_eq!(count(2), 29);
+    assert_eq!(count(3), 15);
+}
+
+#[test_case]
+fn test_alpha_blit() {
+    assert_eq!(blend(rgb(0, 0, 0), rgba(255, 255, 255, 128)), rgb(128, 128, 128));
+
+    let mut pixels = [rgb(0, 0, 200); 4 * 4];
+    let mut framebuffer = test_framebuffer(&mut pixels, 4, 4);
+    let image = Image { width: 2, height: 1, pixels: &[rgba(255, 0, 0, 255), rgba(255, 0, 0, 0)] };
+    Painter::new(&mut framebuffer).blit(&image, 3, 0);
+    assert_eq!(framebuffer.get_pixel(3, 0), rgb(255, 0, 0));
+    assert_eq!(framebuffer.get_pixel(2, 0), rgb(0, 0, 200));
+}
+
+#[test_case]
+fn test_double_buffer_flush() {
+    let mut front = [0u32; 8 * 8];
+    let mut back = [0u32; 8 * 8];
+    let framebuffer = test_framebuffer(&mut front, 8, 8);
+    let mut buffer = DoubleBuffer::new(framebuffer, &mut back).unwrap();
+
+    let mut painter = Painter::new(&mut buffer);
+    painter.fill_rect(Rect::new(1, 1, 2, 2), 7);
+    painter.draw_pixel(2, 2, 9);
+    painter.draw_pixel(6, 6, 5);
+    assert_eq!(buffer.dirty_rects(), &[Rect::new(1, 1, 2, 2), Rect::new(6, 6, 1, 1)]);
+    assert_eq!(framebuffer.get_pixel(1, 1), 0);
+
+    buffer.flush();
+    assert!(buffer.dirty_rects().is_empty());
+    assert_eq!((framebuffer.get_pixel(1, 1), framebuffer.get_pixel(2, 2), framebuffer.get_pixel(6, 6)), (7, 9, 5));
+}
@@ -0,0 +1,217 @@
+use crate::acpi;
+use crate::memory::phys_to_virt;
+use crate::time::Timer;
+use core::ptr;
+use core::time::Duration;
+use spin::Mutex;
+use x86_64::PhysAddr;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/* O HPET (High Precision Event Timer) possui um contador principal de pelo menos 10 MHz e vários
+* comparadores. Quando o contador alcança o valor de um comparador, o timer correspondente gera uma
+* interrupção, uma única vez ou periodicamente. Os registradores são mapeados em memória no endereço
+* informado pela tabela "HPET" do ACPI.
+*
+* No modo de substituição legado (legacy replacement) o timer 0 é ligado na IRQ 0 no lugar do PIT e o
+* timer 1 na IRQ 8 no lugar do RTC, então o manipulador do tick do sistema não precisa mudar. Nesse
+* modo a interrupção periódica do RTC deixa de chegar ao processador.
+*/
+const REG_CAPABILITIES: u64 = 0x000;
+const REG_CONFIGURATION: u64 = 0x010;
+const REG_MAIN_COUNTER: u64 = 0x0f0;
+
+const CONFIG_ENABLE: u64 = 1 << 0;
+const CONFIG_LEGACY_REPLACEMENT: u64 = 1 << 1;
+const CAPABILITY_LEGACY_REPLACEMENT: u64 = 1 << 15;
+
+const TIMER_INTERRUPT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAPABLE: u64 = 1 << 4;
+const TIMER_SET_ACCUMULATOR: u64 = 1 << 6;
+
+const FEMTOSECONDS_PER_SECOND: u64 = 1_000_000_000_000_000;
+
+#[derive(Debug)]
+pub struct Hpet {
+    base: u64,
+    period_fs: u64,                                                                                 // Período do contador principal em femtossegundos
+    timers: u8,
+    legacy_replacement: bool,
+}
+
+fn timer_config(timer: u8) -> u64 {
+    0x100 + 0x20 * u64::from(timer)
+}
+
+fn timer_comparator(timer: u8) -> u64 {
+    0x108 + 0x20 * u64::from(timer)
+}
+
+impl Hpet {
+    // Localiza o HPET através da tabela do ACPI e lê as capacidades do bloco de timers.
+    fn discover() -> Option<Hpet> {
+        let table = acpi::find_table(b"HPET")?.as_u64();
+        let address_space: u8 = unsafe { acpi::read_phys(table + 40) };
+        if address_space != 0 {
+            return None;                                                                            // Os registradores precisam estar na memória
+        }
+        let address: u64 = unsafe { acpi::read_phys(table + 44) };
+
+        let mut hpet = Hpet {
+            base: phys_to_virt(PhysAddr::new(address)).as_u64(),
+            period_fs: 0,
+            timers: 0,
+            legacy_replacement: false,
+        };
+        let capabilities = hpet.read(REG_CAPABILITIES);
+        hpet.period_fs = capabilities >> 32;
+        hpet.timers = ((capabilities >> 8) & 0x1f) as u8 + 1;
+        hpet.legacy_replacement = capabilities & CAPABILITY_LEGACY_REPLACEMENT != 0;
+
+        if hpet.period_fs == 0 || hpet.period_fs > 100_000_000 {
+            return None;                                                                            // A especificação limita o período a 100 ns
+        }
+        Some(hpet)
+    }
+
+    fn read(&self, register: u64) -> u64 {
+        unsafe { ptr::read_volatile((self.base + register) as *const u64) }
+    }
+
+    fn write(&mut self, register: u64, value: u64) {
+        unsafe { ptr::write_volatile((self.base + register) as *mut u64, value) };
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.read(REG_MAIN_COUNTER)
+    }
+
+    pub fn period_fs(&self) -> u64 {
+        self.period_fs
+    }
+
+    pub fn frequency(&self) -> u64 {
+        FEMTOSECONDS_PER_SECOND / self.period_fs
+    }
+
+    pub fn timers(&self) -> u8 {
+        self.timers
+    }
+
+    fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        ((duration.as_nanos() * 1_000_000 / u128::from(self.period_fs)) as u64).max(1)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        let config = self.read(REG_CONFIGURATION);
+        let config = if enabled { config | CONFIG_ENABLE } else { config & !CONFIG_ENABLE };
+        self.write(REG_CONFIGURATION, config);
+    }
+
+    pub fn is_legacy_replacement_active(&self) -> bool {
+        self.read(REG_CONFIGURATION) & CONFIG_LEGACY_REPLACEMENT != 0
+    }
+
+    fn set_legacy_replacement(&mut self) {
+        let config = self.read(REG_CONFIGURATION);
+        self.write(REG_CONFIGURATION, config | CONFIG_LEGACY_REPLACEMENT);
+    }
+
+    /* Programa o timer 0 para disparar periodicamente. Com o bit de acumulador ativo, a primeira
+    * escrita no comparador define o próximo disparo e a segunda define o período. O contador principal
+    * não é zerado, pois o relógio e as medições de tempo dependem dele.
+    */
+    fn start_timer0_periodic(&mut self, ticks: u64) {
+        self.set_enabled(false);
+        let config = self.read(timer_config(0));
+        self.write(timer_config(0), config | TIMER_INTERRUPT_ENABLE | TIMER_PERIODIC | TIMER_SET_ACCUMULATOR);
+        self.write(timer_comparator(0), self.counter().wrapping_add(ticks));
+        self.write(timer_comparator(0), ticks);
+        self.set_legacy_replacement();
+        self.set_enabled(true);
+    }
+
+    fn start_timer0_one_shot(&mut self, ticks: u64) {
+        let config = self.read(timer_config(0)) & !TIMER_PERIODIC;
+        self.write(timer_config(0), config | TIMER_INTERRUPT_ENABLE);
+        self.write(timer_comparator(0), self.counter().wrapping_add(ticks));
+        self.set_legacy_replacement();
+        self.set_enabled(true);
+    }
+}
+
+impl Timer for Hpet {
+    fn name(&self) -> &'static str {
+        "HPET"
+    }
+
+    fn start_periodic(&mut self, frequency_hz: u32) -> Option<Duration> {
+        let periodic = self.read(timer_config(0)) & TIMER_PERIODIC_CAPABLE != 0;
+        if !self.legacy_replacement || !periodic {
+            return None;
+        }
+
+        let ticks = (self.frequency() / u64::from(frequency_hz.max(1))).max(1);
+        self.start_timer0_periodic(ticks);
+        Some(Duration::from_nanos((u128::from(ticks) * u128::from(self.period_fs) / 1_000_000) as u64))
+    }
+
+    fn start_one_shot(&mut self, delay: Duration) -> bool {
+        if !self.legacy_replacement {
+            return false;
+        }
+        let ticks = self.duration_to_ticks(delay);
+        self.start_timer0_one_shot(ticks);
+        true
+    }
+
+    fn stop(&mut self) {
+        let config = self.read(timer_config(0));
+        self.write(timer_config(0), config & !(TIMER_INTERRUPT_ENABLE | TIMER_PERIODIC));
+    }
+}
+
+pub static HPET: Mutex<Option<Hpet>> = Mutex::new(None);
+
+/* Procura o HPET e habilita o contador principal, que pode ser utilizado como relógio mesmo quando o
+* HPET não é a fonte do tick do sistema. Retorna false quando a máquina não possui HPET.
+*/
+pub fn init() -> bool {
+    match Hpet::discover() {
+        Some(mut hpet) => {
+            hpet.set_enabled(true);
+            without_interrupts(|| *HPET.lock() = Some(hpet));
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn is_present() -> bool {
+    without_interrupts(|| HPET.lock().is_some())
+}
+
+pub fn counter() -> Option<u64> {
+    without_interrupts(|| HPET.lock().as_ref().map(Hpet::counter))
+}
+
+// Verifica se o HPET ocupa as IRQs 0 e 8 no lugar do PIT e do RTC.
+pub fn legacy_replacement_active() -> bool {
+    without_interrupts(|| HPET.lock().as_ref().is_some_and(Hpet::is_legacy_replacement_active))
+}
+
+#[test_case]
+fn test_counter_advances() {
+    if let Some(start) = counter() {
+        crate::time::sleep(2);
+        assert!(counter().unwrap() > start);
+    }
+}
+
+#[test_case]
+fn test_period_within_specification() {
+    if let Some(hpet) = HPET.lock().as_ref() {
+        assert!(hpet.period_fs() > 0 && hpet.period_fs() <= 100_000_000);
+        assert!(hpet.timers() >= 3);                                                                // Todo HPET possui pelo menos 3 timers
+    }
+}
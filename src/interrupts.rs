@@ -1,11 +1,152 @@
-use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
-use crate::println;
+use x86_64::VirtAddr;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode, SelectorErrorCode};
+use crate::{println, serial_println};
+use crate::{apic, gdt};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use lazy_static::lazy_static;
+use pic8259::ChainedPics;
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+/* Por padrão os PICs entregam as IRQs nos vetores 0 a 15, que já são utilizados pelas exceções da
+* CPU. Por isso remapeamos as interrupções de hardware para os vetores 32 a 47, logo após as 32
+* entradas reservadas para exceções.
+*/
+pub const PIC_1_OFFSET: u8 = 32;
+pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+pub const IRQ_COUNT: usize = 16;
+
+/* O PIC secundário fica conectado na linha 2 do primário (cascata), somando 15 linhas utilizáveis.
+* O Mutex garante acesso exclusivo às portas de comando e dados dos controladores.
+*/
+pub static PICS: Mutex<ChainedPics> = Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
+
+// Linhas de IRQ do barramento ISA e o vetor da IDT em que cada uma é entregue.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InterruptIndex {
+    Timer = PIC_1_OFFSET,
+    Keyboard,
+    Cascade,
+    Com2,
+    Com1,
+    Lpt2,
+    Floppy,
+    Lpt1,
+    RealTimeClock = PIC_2_OFFSET,
+    Acpi,
+    Free1,
+    Free2,
+    Mouse,
+    Coprocessor,
+    PrimaryAta,
+    SecondaryAta,
+}
+
+impl InterruptIndex {
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub fn as_usize(self) -> usize {
+        usize::from(self.as_u8())
+    }
+
+    // Número da linha de IRQ (0 a 15) correspondente ao vetor.
+    pub fn irq(self) -> u8 {
+        self.as_u8() - PIC_1_OFFSET
+    }
+}
+
+/* Função chamada quando uma IRQ registrada é disparada. Ela executa no contexto da interrupção,
+* então deve ser curta e não pode esperar por locks que o código interrompido possa estar segurando.
+*/
+pub type IrqHandler = fn();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+    InvalidLine(u8),                                                                                // A linha não existe ou é a cascata do PIC secundário
+    AlreadyRegistered(u8),
+    Rerouted(u8),                                                                                   // A linha foi tomada pelo HPET no modo de substituição legado
+}
+
+static IRQ_HANDLERS: Mutex<[Option<IrqHandler>; IRQ_COUNT]> = Mutex::new([None; IRQ_COUNT]);
+
+// Quantidade de interrupções recebidas em cada linha, incluindo as que não possuem manipulador.
+static IRQ_COUNTS: [AtomicU64; IRQ_COUNT] = [const { AtomicU64::new(0) }; IRQ_COUNT];
+
+// Controlador responsável por entregar as IRQs, escolhido durante a inicialização.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptController {
+    Pic,
+    Apic,
+}
+
+static APIC_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn controller() -> InterruptController {
+    if APIC_ACTIVE.load(Ordering::SeqCst) {
+        InterruptController::Apic
+    } else {
+        InterruptController::Pic
+    }
+}
 
 lazy_static!{
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
+        idt.divide_error.set_handler_fn(divide_error_handler);
+        idt.debug.set_handler_fn(debug_handler);
+        idt.non_maskable_interrupt.set_handler_fn(non_maskable_interrupt_handler);
         idt.breakpoint.set_handler_fn(breakpoint_handler);
+        idt.overflow.set_handler_fn(overflow_handler);
+        idt.bound_range_exceeded.set_handler_fn(bound_range_exceeded_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
+        idt.device_not_available.set_handler_fn(device_not_available_handler);
+        unsafe {
+            /* O double fault utiliza uma pilha própria da IST. Assim, mesmo que a exceção tenha
+            * sido causada por um estouro da pilha do kernel, o manipulador ainda consegue executar
+            * em vez de gerar um triple fault (que reinicia a máquina).
+            */
+            idt.double_fault.set_handler_fn(double_fault_handler)
+                .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt.invalid_tss.set_handler_fn(invalid_tss_handler);
+        idt.segment_not_present.set_handler_fn(segment_not_present_handler);
+        idt.stack_segment_fault.set_handler_fn(stack_segment_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
+        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.x87_floating_point.set_handler_fn(x87_floating_point_handler);
+        idt.alignment_check.set_handler_fn(alignment_check_handler);
+        idt.machine_check.set_handler_fn(machine_check_handler);
+        idt.simd_floating_point.set_handler_fn(simd_floating_point_handler);
+        idt.virtualization.set_handler_fn(virtualization_handler);
+        idt.cp_protection_exception.set_handler_fn(cp_protection_handler);
+        idt.hv_injection_exception.set_handler_fn(hv_injection_handler);
+        idt.vmm_communication_exception.set_handler_fn(vmm_communication_handler);
+        idt.security_exception.set_handler_fn(security_exception_handler);
+
+        /* Cada linha de IRQ possui seu próprio manipulador, que apenas encaminha a interrupção para
+        * a função registrada pelo driver através de register_irq.
+        */
+        idt[InterruptIndex::Timer.as_usize()].set_handler_fn(irq_handler::<0>);
+        idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(irq_handler::<1>);
+        idt[InterruptIndex::Cascade.as_usize()].set_handler_fn(irq_handler::<2>);
+        idt[InterruptIndex::Com2.as_usize()].set_handler_fn(irq_handler::<3>);
+        idt[InterruptIndex::Com1.as_usize()].set_handler_fn(irq_handler::<4>);
+        idt[InterruptIndex::Lpt2.as_usize()].set_handler_fn(irq_handler::<5>);
+        idt[InterruptIndex::Floppy.as_usize()].set_handler_fn(irq_handler::<6>);
+        idt[InterruptIndex::Lpt1.as_usize()].set_handler_fn(irq_handler::<7>);
+        idt[InterruptIndex::RealTimeClock.as_usize()].set_handler_fn(irq_handler::<8>);
+        idt[InterruptIndex::Acpi.as_usize()].set_handler_fn(irq_handler::<9>);
+        idt[InterruptIndex::Free1.as_usize()].set_handler_fn(irq_handler::<10>);
+        idt[InterruptIndex::Free2.as_usize()].set_handler_fn(irq_handler::<11>);
+        idt[InterruptIndex::Mouse.as_usize()].set_handler_fn(irq_handler::<12>);
+        idt[InterruptIndex::Coprocessor.as_usize()].set_handler_fn(irq_handler::<13>);
+        idt[InterruptIndex::PrimaryAta.as_usize()].set_handler_fn(irq_handler::<14>);
+        idt[InterruptIndex::SecondaryAta.as_usize()].set_handler_fn(irq_handler::<15>);
+        idt[usize::from(apic::SPURIOUS_VECTOR)].set_handler_fn(spurious_interrupt_handler);
         idt
     };
 }
@@ -14,11 +155,413 @@ pub fn init_idt() {
     IDT.load();
 }
 
+/* Inicializa os PICs com o remapeamento dos vetores. Todas as linhas começam mascaradas, exceto a
+* cascata, e cada uma é liberada quando um driver a registra.
+*/
+pub fn init_pics() {
+    unsafe {
+        let mut pics = PICS.lock();
+        pics.initialize();
+        pics.write_masks(!(1 << InterruptIndex::Cascade.irq()), 0xff);
+    }
+}
+
+/* Escolhe o controlador de interrupções através da opção "interrupts" da linha de comando: "pic"
+* força o 8259 e "apic" (ou a ausência da opção) utiliza o APIC quando disponível. Os PICs são sempre
+* remapeados, pois mesmo mascarados podem gerar interrupções espúrias nos vetores das exceções.
+*/
+pub fn init_controller() {
+    init_pics();
+
+    if crate::cmdline::get("interrupts") == Some("pic") {
+        return;
+    }
+    match apic::init() {
+        Ok(()) => {
+            unsafe { PICS.lock().disable() };                                                       // Mascara todas as linhas do 8259
+            APIC_ACTIVE.store(true, Ordering::SeqCst);
+        }
+        Err(error) => println!("APIC indisponivel ({:?}), utilizando o PIC 8259", error),
+    }
+}
+
+/* Registra o manipulador de uma linha de IRQ e desmascara a linha no controlador. Cada linha pode ter apenas
+* um dono, então registrar uma linha já ocupada retorna erro.
+*/
+pub fn register_irq(irq: u8, handler: IrqHandler) -> Result<(), IrqError> {
+    if usize::from(irq) >= IRQ_COUNT || irq == InterruptIndex::Cascade.irq() {
+        return Err(IrqError::InvalidLine(irq));
+    }
+
+    x86_64::instructions::interrupts::without_interrupts(|| {                                      // Evita deadlock caso a IRQ dispare enquanto o lock está ativo.
+        let mut handlers = IRQ_HANDLERS.lock();
+        if handlers[usize::from(irq)].is_some() {
+            return Err(IrqError::AlreadyRegistered(irq));
+        }
+        handlers[usize::from(irq)] = Some(handler);
+        set_irq_masked(irq, false);
+        Ok(())
+    })
+}
+
+// Remove o manipulador de uma linha de IRQ e volta a mascará-la.
+pub fn unregister_irq(irq: u8) {
+    if usize::from(irq) >= IRQ_COUNT || irq == InterruptIndex::Cascade.irq() {
+        return;
+    }
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        IRQ_HANDLERS.lock()[usize::from(irq)] = None;
+        set_irq_masked(irq, true);
+    });
+}
+
+pub fn is_irq_registered(irq: u8) -> bool {
+    usize::from(irq) < IRQ_COUNT
+        && x86_64::instructions::interrupts::without_interrupts(|| IRQ_HANDLERS.lock()[usize::from(irq)].is_some())
+}
+
+pub fn irq_count(irq: u8) -> u64 {
+    IRQ_COUNTS.get(usize::from(irq)).map_or(0, |count| count.load(Ordering::Relaxed))
+}
+
+fn set_irq_masked(irq: u8, masked: bool) {
+    if controller() == InterruptController::Apic {
+        apic::set_irq_masked(irq, masked);
+        return;
+    }
+
+    let mut pics = PICS.lock();
+    let mut masks = unsafe { pics.read_masks() };
+    let (pic, bit) = (usize::from(irq / 8), irq % 8);
+    if masked {
+        masks[pic] |= 1 << bit;
+    } else {
+        masks[pic] &= !(1 << bit);
+    }
+    unsafe { pics.write_masks(masks[0], masks[1]) };
+}
+
+/* O controlador aguarda o sinal de fim de interrupção (EOI) antes de entregar a próxima IRQ. Quando a
+* IRQ vem do PIC secundário, a crate envia o EOI para os dois controladores.
+*/
+fn end_of_interrupt(irq: u8) {
+    match controller() {
+        InterruptController::Apic => apic::end_of_interrupt(),
+        InterruptController::Pic => unsafe {
+            PICS.lock().notify_end_of_interrupt(PIC_1_OFFSET + irq);
+        },
+    }
+}
+
+const PIC_1_COMMAND: u16 = 0x20;
+const PIC_2_COMMAND: u16 = 0xa0;
+const PIC_READ_ISR: u8 = 0x0b;                                                                      // OCW3: a próxima leitura da porta de comando retorna o ISR
+
+/* Quando a linha de uma IRQ é desativada antes da CPU confirmar a interrupção, o 8259 entrega a IRQ
+* de menor prioridade do controlador (7 no primário e 15 no secundário) sem marcá-la no ISR (In
+* Service Register). Essas IRQs espúrias não são repassadas ao manipulador e não recebem EOI, que
+* confirmaria outra IRQ ainda em atendimento. No caso da IRQ 15 o primário recebeu uma IRQ real na
+* cascata, então apenas ele recebe o EOI.
+*/
+fn is_spurious_pic_irq(irq: u8) -> bool {
+    if controller() != InterruptController::Pic || (irq != 7 && irq != 15) {
+        return false;
+    }
+    let _pics = PICS.lock();
+    let mut command = Port::<u8>::new(if irq == 7 { PIC_1_COMMAND } else { PIC_2_COMMAND });
+    unsafe {
+        command.write(PIC_READ_ISR);
+        command.read() & (1 << (irq % 8)) == 0
+    }
+}
+
+extern "x86-interrupt" fn irq_handler<const IRQ: u8>(_stack_frame: InterruptStackFrame) {
+    if is_spurious_pic_irq(IRQ) {
+        if IRQ == 15 {
+            unsafe { PICS.lock().notify_end_of_interrupt(InterruptIndex::Cascade.as_u8()) };
+        }
+        return;
+    }
+    IRQ_COUNTS[usize::from(IRQ)].fetch_add(1, Ordering::Relaxed);
+    let handler = IRQ_HANDLERS.lock()[usize::from(IRQ)];
+    if let Some(handler) = handler {
+        handler();
+    }
+    end_of_interrupt(IRQ);
+}
+
+// Interrupções espúrias do APIC não devem receber EOI.
+extern "x86-interrupt" fn spurious_interrupt_handler(_stack_frame: InterruptStackFrame) {}
+
+/* Imprime o relatório da exceção tanto no buffer VGA quanto na porta serial, assim a saída também
+* aparece no host quando o qemu é executado com -serial stdio.
+*/
+macro_rules! exception_println {
+    ($($arg:tt)*) => {{
+        println!($($arg)*);
+        serial_println!($($arg)*);
+    }};
+}
+
+fn report(name: &str, stack_frame: &InterruptStackFrame) {
+    exception_println!("EXCEPTION: {}\n{:#?}", name, stack_frame);
+}
+
+/* Os códigos de erro de #TS, #NP, #SS e #GP referenciam o seletor de segmento que causou a exceção.
+* Um código de erro zero significa que a exceção não foi causada por um seletor.
+*/
+fn report_selector_error(error_code: u64) {
+    match SelectorErrorCode::new(error_code) {
+        Some(selector) if !selector.is_null() => exception_println!(
+            "Seletor: indice {:#x}, tabela {:?}, externo: {}",
+            selector.index(), selector.descriptor_table(), selector.external()
+        ),
+        _ => exception_println!("Codigo de erro: {:#x}", error_code),
+    }
+}
+
+/* Exceções do tipo fault (falha) não podem ser recuperadas enquanto não houver uma forma de corrigir
+* a causa, já que ao retornar a CPU executaria novamente a mesma instrução. Então imprimimos o
+* relatório e paramos a CPU.
+*/
+fn fault(name: &str, stack_frame: &InterruptStackFrame) -> ! {
+    report(name, stack_frame);
+    crate::hlt_loop();
+}
+
+fn fault_with_error_code(name: &str, stack_frame: &InterruptStackFrame, error_code: u64) -> ! {
+    report(name, stack_frame);
+    exception_println!("Codigo de erro: {:#x}", error_code);
+    crate::hlt_loop();
+}
+
+fn fault_with_selector(name: &str, stack_frame: &InterruptStackFrame, error_code: u64) -> ! {
+    report(name, stack_frame);
+    report_selector_error(error_code);
+    crate::hlt_loop();
+}
+
+// Exceções recuperáveis: após o relatório a execução continua na instrução seguinte.
+
+extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
+    report("DEBUG (#DB, vetor 1)", &stack_frame);
+}
+
+extern "x86-interrupt" fn non_maskable_interrupt_handler(stack_frame: InterruptStackFrame) {
+    report("NON MASKABLE INTERRUPT (NMI, vetor 2)", &stack_frame);
+}
+
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
-    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+    report("BREAKPOINT (#BP, vetor 3)", &stack_frame);
+}
+
+extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
+    report("OVERFLOW (#OF, vetor 4)", &stack_frame);
+}
+
+// Exceções irrecuperáveis.
+
+extern "x86-interrupt" fn divide_error_handler(stack_frame: InterruptStackFrame) {
+    fault("DIVIDE ERROR (#DE, vetor 0)", &stack_frame);
+}
+
+extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: InterruptStackFrame) {
+    fault("BOUND RANGE EXCEEDED (#BR, vetor 5)", &stack_frame);
+}
+
+extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFrame) {
+    fault("INVALID OPCODE (#UD, vetor 6)", &stack_frame);
+}
+
+extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
+    fault("DEVICE NOT AVAILABLE (#NM, vetor 7)", &stack_frame);
+}
+
+/* O double fault ocorre quando a CPU falha ao invocar o manipulador de uma exceção. O código de
+* erro é sempre 0 e não é possível retornar da exceção, por isso a função é divergente.
+*/
+extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) -> ! {
+    fault("DOUBLE FAULT (#DF, vetor 8)", &stack_frame);
+}
+
+extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_selector("INVALID TSS (#TS, vetor 10)", &stack_frame, error_code);
+}
+
+extern "x86-interrupt" fn segment_not_present_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_selector("SEGMENT NOT PRESENT (#NP, vetor 11)", &stack_frame, error_code);
+}
+
+extern "x86-interrupt" fn stack_segment_fault_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_selector("STACK SEGMENT FAULT (#SS, vetor 12)", &stack_frame, error_code);
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_selector("GENERAL PROTECTION FAULT (#GP, vetor 13)", &stack_frame, error_code);
+}
+
+/* Função que pode resolver um page fault, por exemplo mapeando a página que está faltando. Ela recebe
+* o endereço acessado e o código de erro e retorna true quando a falha foi resolvida, assim a CPU
+* executa novamente a instrução que falhou.
+*/
+pub type PageFaultResolver = fn(VirtAddr, PageFaultErrorCode) -> bool;
+
+static PAGE_FAULT_RESOLVER: Mutex<Option<PageFaultResolver>> = Mutex::new(None);
+
+/* Registra o resolvedor de page faults. Enquanto não houver um subsistema de memória, nenhum
+* resolvedor é registrado e todo page fault é tratado como irrecuperável.
+*/
+pub fn set_page_fault_resolver(resolver: PageFaultResolver) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        *PAGE_FAULT_RESOLVER.lock() = Some(resolver);
+    });
+}
+
+/* Quando ocorre um page fault a CPU armazena no registrador CR2 o endereço virtual que causou a
+* falha. O código de erro descreve o tipo de acesso realizado.
+*/
+extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+    use x86_64::registers::control::Cr2;
+
+    let address = Cr2::read();
+    let resolver = *PAGE_FAULT_RESOLVER.lock();
+    if let Some(resolver) = resolver {
+        if resolver(address, error_code) {
+            return;
+        }
+    }
+
+    report("PAGE FAULT (#PF, vetor 14)", &stack_frame);
+    exception_println!("Endereco acessado: {:?}", address);
+    exception_println!("Instrucao: {:?}", stack_frame.instruction_pointer);
+    exception_println!("Codigo de erro: {:?}", error_code);
+    describe_page_fault(error_code);
+    crate::hlt_loop();
+}
+
+fn describe_page_fault(error_code: PageFaultErrorCode) {
+    let cause = if error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) {
+        "violacao de protecao"
+    } else {
+        "pagina nao presente"
+    };
+    let access = if error_code.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+        "busca de instrucao"
+    } else if error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE) {
+        "escrita"
+    } else {
+        "leitura"
+    };
+    let mode = if error_code.contains(PageFaultErrorCode::USER_MODE) {
+        "usuario"
+    } else {
+        "supervisor"
+    };
+    exception_println!("Causa: {}, acesso: {}, modo: {}", cause, access, mode);
+
+    if error_code.contains(PageFaultErrorCode::MALFORMED_TABLE) {
+        exception_println!("Bit reservado ativo em uma entrada da tabela de paginas");
+    }
+    if error_code.contains(PageFaultErrorCode::PROTECTION_KEY) {
+        exception_println!("Acesso negado pela chave de protecao da pagina");
+    }
+}
+
+extern "x86-interrupt" fn x87_floating_point_handler(stack_frame: InterruptStackFrame) {
+    fault("X87 FLOATING POINT (#MF, vetor 16)", &stack_frame);
+}
+
+extern "x86-interrupt" fn alignment_check_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_error_code("ALIGNMENT CHECK (#AC, vetor 17)", &stack_frame, error_code);
+}
+
+// O machine check indica um erro de hardware detectado pela CPU e também não permite retorno.
+extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+    fault("MACHINE CHECK (#MC, vetor 18)", &stack_frame);
+}
+
+extern "x86-interrupt" fn simd_floating_point_handler(stack_frame: InterruptStackFrame) {
+    fault("SIMD FLOATING POINT (#XM, vetor 19)", &stack_frame);
+}
+
+extern "x86-interrupt" fn virtualization_handler(stack_frame: InterruptStackFrame) {
+    fault("VIRTUALIZATION (#VE, vetor 20)", &stack_frame);
+}
+
+extern "x86-interrupt" fn cp_protection_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_error_code("CONTROL PROTECTION (#CP, vetor 21)", &stack_frame, error_code);
+}
+
+extern "x86-interrupt" fn hv_injection_handler(stack_frame: InterruptStackFrame) {
+    fault("HYPERVISOR INJECTION (#HV, vetor 28)", &stack_frame);
+}
+
+extern "x86-interrupt" fn vmm_communication_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_error_code("VMM COMMUNICATION (#VC, vetor 29)", &stack_frame, error_code);
+}
+
+extern "x86-interrupt" fn security_exception_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    fault_with_error_code("SECURITY EXCEPTION (#SX, vetor 30)", &stack_frame, error_code);
 }
 
 #[test_case]
 fn test_breakpoint_exception() {
     x86_64::instructions::interrupts::int3();
-}
\ No newline at end of file
+}
+
+#[test_case]
+fn test_debug_exception() {
+    unsafe { core::arch::asm!("int 1", options(nomem, nostack)); }
+}
+
+#[test_case]
+fn test_non_maskable_interrupt() {
+    unsafe { core::arch::asm!("int 2", options(nomem, nostack)); }
+}
+
+#[test_case]
+fn test_overflow_exception() {
+    unsafe { core::arch::asm!("int 4", options(nomem, nostack)); }
+}
+
+#[test_case]
+fn test_register_irq_dispatch() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static CALLED: AtomicBool = AtomicBool::new(false);
+    fn handler() {
+        CALLED.store(true, Ordering::SeqCst);
+    }
+
+    /* A IRQ 11 não é utilizada por nenhum dispositivo do qemu, então disparamos o vetor dela através
+    * de uma interrupção de software.
+    */
+    let count = irq_count(InterruptIndex::Free2.irq());
+    register_irq(InterruptIndex::Free2.irq(), handler).unwrap();
+    assert!(is_irq_registered(InterruptIndex::Free2.irq()));
+    unsafe { core::arch::asm!("int 43", options(nomem, nostack)); }
+    unregister_irq(InterruptIndex::Free2.irq());
+    assert!(CALLED.load(Ordering::SeqCst));
+    assert_eq!(irq_count(InterruptIndex::Free2.irq()), count + 1);
+}
+
+#[test_case]
+fn test_register_irq_errors() {
+    fn handler() {}
+
+    assert_eq!(register_irq(InterruptIndex::Cascade.irq(), handler), Err(IrqError::InvalidLine(2)));
+    assert_eq!(register_irq(16, handler), Err(IrqError::InvalidLine(16)));
+
+    let irq = InterruptIndex::Free1.irq();
+    register_irq(irq, handler).unwrap();
+    assert_eq!(register_irq(irq, handler), Err(IrqError::AlreadyRegistered(irq)));
+    unregister_irq(irq);
+}
+#[test_case]
+fn test_spurious_pic_irq() {
+    assert!(!is_spurious_pic_irq(InterruptIndex::Keyboard.irq()));
+    if controller() == InterruptController::Pic {
+        assert!(x86_64::instructions::interrupts::without_interrupts(|| is_spurious_pic_irq(7)));   // Fora de um manipulador a IRQ 7 não está em atendimento
+    }
+}
@@ -0,0 +1,587 @@
+use crate::interrupts::{self, InterruptIndex};
+use crate::keyboard_layout::{self, DeadKey, DecodedKey, KeyboardLayout};
+use crate::ps2::{self, DEVICE_ACK, DEVICE_RESEND};
+use crate::{cmdline, vga_buffer};
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/* O teclado PS/2 envia um ou mais bytes (scancodes) pela porta 0x60 a cada tecla pressionada ou
+* solta, e o controlador gera a IRQ 1. Existem dois conjuntos de scancodes em uso: o set 2 é o padrão
+* do teclado, mas o controlador normalmente traduz os códigos para o set 1 do antigo IBM XT.
+*
+* Set 1: o bit 7 indica que a tecla foi solta e as teclas estendidas são prefixadas com 0xe0.
+* Set 2: a tecla solta é prefixada com 0xf0 e as estendidas também com 0xe0.
+*/
+const KEYBOARD_SET_LEDS: u8 = 0xed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScancodeSet {
+    Set1,
+    Set2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    PrintScreen, ScrollLock, Pause,
+    Backtick, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, Minus, Equals, Backspace,
+    Tab, Q, W, E, R, T, Y, U, I, O, P, BracketLeft, BracketRight, Backslash,
+    CapsLock, A, S, D, F, G, H, J, K, L, Semicolon, Quote, Enter,
+    LeftShift, NonUsBackslash, Z, X, C, V, B, N, M, Comma, Period, Slash, RightShift,
+    LeftCtrl, LeftGui, LeftAlt, Space, RightAlt, RightGui, Menu, RightCtrl,
+    Insert, Delete, Home, End, PageUp, PageDown, ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    NumLock, KeypadSlash, KeypadMultiply, KeypadMinus, KeypadPlus, KeypadEnter, KeypadPeriod,
+    Keypad0, Keypad1, Keypad2, Keypad3, Keypad4, Keypad5, Keypad6, Keypad7, Keypad8, Keypad9,
+    International1,                                                                                 // Tecla "/?" do ABNT2, ao lado do Shift direito
+    KeypadComma,                                                                                    // Tecla "." do teclado numérico do ABNT2
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Down,
+    Up,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    pub const fn new() -> Modifiers {
+        Modifiers {
+            left_shift: false,
+            right_shift: false,
+            left_ctrl: false,
+            right_ctrl: false,
+            left_alt: false,
+            right_alt: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+        }
+    }
+
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+
+    pub fn alt(&self) -> bool {
+        self.left_alt || self.right_alt
+    }
+
+    // Bits do comando 0xed: 0 = Scroll Lock, 1 = Num Lock, 2 = Caps Lock.
+    fn leds(&self) -> u8 {
+        (self.scroll_lock as u8) | (self.num_lock as u8) << 1 | (self.caps_lock as u8) << 2
+    }
+}
+
+// Evento de tecla com o estado dos modificadores no momento em que ocorreu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub state: KeyState,
+    pub modifiers: Modifiers,
+}
+
+fn set1_key(scancode: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = match scancode {
+        0x01 => Escape, 0x02 => Key1, 0x03 => Key2, 0x04 => Key3, 0x05 => Key4, 0x06 => Key5,
+        0x07 => Key6, 0x08 => Key7, 0x09 => Key8, 0x0a => Key9, 0x0b => Key0, 0x0c => Minus,
+        0x0d => Equals, 0x0e => Backspace, 0x0f => Tab, 0x10 => Q, 0x11 => W, 0x12 => E,
+        0x13 => R, 0x14 => T, 0x15 => Y, 0x16 => U, 0x17 => I, 0x18 => O, 0x19 => P,
+        0x1a => BracketLeft, 0x1b => BracketRight, 0x1c => Enter, 0x1d => LeftCtrl, 0x1e => A,
+        0x1f => S, 0x20 => D, 0x21 => F, 0x22 => G, 0x23 => H, 0x24 => J, 0x25 => K, 0x26 => L,
+        0x27 => Semicolon, 0x28 => Quote, 0x29 => Backtick, 0x2a => LeftShift, 0x2b => Backslash,
+        0x2c => Z, 0x2d => X, 0x2e => C, 0x2f => V, 0x30 => B, 0x31 => N, 0x32 => M,
+        0x33 => Comma, 0x34 => Period, 0x35 => Slash, 0x36 => RightShift, 0x37 => KeypadMultiply,
+        0x38 => LeftAlt, 0x39 => Space, 0x3a => CapsLock, 0x3b => F1, 0x3c => F2, 0x3d => F3,
+        0x3e => F4, 0x3f => F5, 0x40 => F6, 0x41 => F7, 0x42 => F8, 0x43 => F9, 0x44 => F10,
+        0x45 => NumLock, 0x46 => ScrollLock, 0x47 => Keypad7, 0x48 => Keypad8, 0x49 => Keypad9,
+        0x4a => KeypadMinus, 0x4b => Keypad4, 0x4c => Keypad5, 0x4d => Keypad6, 0x4e => KeypadPlus,
+        0x4f => Keypad1, 0x50 => Keypad2, 0x51 => Keypad3, 0x52 => Keypad0, 0x53 => KeypadPeriod,
+        0x56 => NonUsBackslash, 0x57 => F11, 0x58 => F12, 0x73 => International1,
+        0x7e => KeypadComma,
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn set1_extended_key(scancode: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = match scancode {
+        0x1c => KeypadEnter, 0x1d => RightCtrl, 0x35 => KeypadSlash, 0x37 => PrintScreen,
+        0x38 => RightAlt, 0x47 => Home, 0x48 => ArrowUp, 0x49 => PageUp, 0x4b => ArrowLeft,
+        0x4d => ArrowRight, 0x4f => End, 0x50 => ArrowDown, 0x51 => PageDown, 0x52 => Insert,
+        0x53 => Delete, 0x5b => LeftGui, 0x5c => RightGui, 0x5d => Menu,
+        _ => return None,                                                                           // Inclui o Shift falso (0x2a) enviado junto do Print Screen
+    };
+    Some(code)
+}
+
+fn set2_key(scancode: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = match scancode {
+        0x76 => Escape, 0x05 => F1, 0x06 => F2, 0x04 => F3, 0x0c => F4, 0x03 => F5, 0x0b => F6,
+        0x83 => F7, 0x0a => F8, 0x01 => F9, 0x09 => F10, 0x78 => F11, 0x07 => F12,
+        0x0e => Backtick, 0x16 => Key1, 0x1e => Key2, 0x26 => Key3, 0x25 => Key4, 0x2e => Key5,
+        0x36 => Key6, 0x3d => Key7, 0x3e => Key8, 0x46 => Key9, 0x45 => Key0, 0x4e => Minus,
+        0x55 => Equals, 0x66 => Backspace, 0x0d => Tab, 0x15 => Q, 0x1d => W, 0x24 => E,
+        0x2d => R, 0x2c => T, 0x35 => Y, 0x3c => U, 0x43 => I, 0x44 => O, 0x4d => P,
+        0x54 => BracketLeft, 0x5b => BracketRight, 0x5d => Backslash, 0x58 => CapsLock,
+        0x1c => A, 0x1b => S, 0x23 => D, 0x2b => F, 0x34 => G, 0x33 => H, 0x3b => J, 0x42 => K,
+        0x4b => L, 0x4c => Semicolon, 0x52 => Quote, 0x5a => Enter, 0x12 => LeftShift,
+        0x61 => NonUsBackslash, 0x1a => Z, 0x22 => X, 0x21 => C, 0x2a => V, 0x32 => B, 0x31 => N,
+        0x3a => M, 0x41 => Comma, 0x49 => Period, 0x4a => Slash, 0x59 => RightShift,
+        0x14 => LeftCtrl, 0x11 => LeftAlt, 0x29 => Space, 0x77 => NumLock, 0x7e => ScrollLock,
+        0x7c => KeypadMultiply, 0x7b => KeypadMinus, 0x79 => KeypadPlus, 0x71 => KeypadPeriod,
+        0x70 => Keypad0, 0x69 => Keypad1, 0x72 => Keypad2, 0x7a => Keypad3, 0x6b => Keypad4,
+        0x73 => Keypad5, 0x74 => Keypad6, 0x6c => Keypad7, 0x75 => Keypad8, 0x7d => Keypad9,
+        0x51 => International1, 0x6d => KeypadComma,
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn set2_extended_key(scancode: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = match scancode {
+        0x14 => RightCtrl, 0x11 => RightAlt, 0x1f => LeftGui, 0x27 => RightGui, 0x2f => Menu,
+        0x4a => KeypadSlash, 0x5a => KeypadEnter, 0x70 => Insert, 0x71 => Delete, 0x6c => Home,
+        0x69 => End, 0x7d => PageUp, 0x7a => PageDown, 0x75 => ArrowUp, 0x72 => ArrowDown,
+        0x6b => ArrowLeft, 0x74 => ArrowRight, 0x7c => PrintScreen,
+        _ => return None,                                                                           // Inclui o Shift falso (0x12) enviado junto do Print Screen
+    };
+    Some(code)
+}
+
+/* Converte a sequência de scancodes em eventos de tecla e mantém o estado dos modificadores. As
+* teclas de trava (Caps, Num e Scroll Lock) alternam apenas na primeira vez que são pressionadas,
+* ignorando a repetição automática enquanto permanecem seguradas.
+*/
+pub struct Decoder {
+    set: ScancodeSet,
+    extended: bool,
+    release: bool,
+    pause_bytes: u8,                                                                                // Bytes restantes da sequência da tecla Pause
+    locks_held: [bool; 3],
+    modifiers: Modifiers,
+}
+
+impl Decoder {
+    pub const fn new(set: ScancodeSet) -> Decoder {
+        Decoder {
+            set,
+            extended: false,
+            release: false,
+            pause_bytes: 0,
+            locks_held: [false; 3],
+            modifiers: Modifiers::new(),
+        }
+    }
+
+    pub fn set_scancode_set(&mut self, set: ScancodeSet) {
+        self.set = set;
+        self.extended = false;
+        self.release = false;
+        self.pause_bytes = 0;
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<KeyEvent> {
+        if self.pause_bytes > 0 {
+            self.pause_bytes -= 1;
+            return None;
+        }
+
+        let (code, state) = match (self.set, byte) {
+            (_, 0xe0) => {
+                self.extended = true;
+                return None;
+            }
+            (ScancodeSet::Set1, 0xe1) => {
+                self.pause_bytes = 5;                                                               // e1 1d 45 e1 9d c5
+                return Some(self.event(KeyCode::Pause, KeyState::Down));
+            }
+            (ScancodeSet::Set2, 0xe1) => {
+                self.pause_bytes = 7;                                                               // e1 14 77 e1 f0 14 f0 77
+                return Some(self.event(KeyCode::Pause, KeyState::Down));
+            }
+            (ScancodeSet::Set2, 0xf0) => {
+                self.release = true;
+                return None;
+            }
+            (ScancodeSet::Set1, byte) => {
+                let state = if byte & 0x80 != 0 { KeyState::Up } else { KeyState::Down };
+                let scancode = byte & 0x7f;
+                let code = if self.extended { set1_extended_key(scancode) } else { set1_key(scancode) };
+                (code, state)
+            }
+            (ScancodeSet::Set2, byte) => {
+                let state = if self.release { KeyState::Up } else { KeyState::Down };
+                let code = if self.extended { set2_extended_key(byte) } else { set2_key(byte) };
+                (code, state)
+            }
+        };
+        self.extended = false;
+        self.release = false;
+
+        let code = code?;
+        self.update_modifiers(code, state);
+        Some(self.event(code, state))
+    }
+
+    fn event(&self, code: KeyCode, state: KeyState) -> KeyEvent {
+        KeyEvent { code, state, modifiers: self.modifiers }
+    }
+
+    fn update_modifiers(&mut self, code: KeyCode, state: KeyState) {
+        let down = state == KeyState::Down;
+        let modifiers = &mut self.modifiers;
+        match code {
+            KeyCode::LeftShift => modifiers.left_shift = down,
+            KeyCode::RightShift => modifiers.right_shift = down,
+            KeyCode::LeftCtrl => modifiers.left_ctrl = down,
+            KeyCode::RightCtrl => modifiers.right_ctrl = down,
+            KeyCode::LeftAlt => modifiers.left_alt = down,
+            KeyCode::RightAlt => modifiers.right_alt = down,
+            KeyCode::CapsLock | KeyCode::NumLock | KeyCode::ScrollLock => {
+                let (index, lock) = match code {
+                    KeyCode::CapsLock => (0, &mut modifiers.caps_lock),
+                    KeyCode::NumLock => (1, &mut modifiers.num_lock),
+                    _ => (2, &mut modifiers.scroll_lock),
+                };
+                if down && !self.locks_held[index] {
+                    *lock = !*lock;
+                }
+                self.locks_held[index] = down;
+            }
+            _ => {}
+        }
+    }
+}
+
+/* Converte os eventos em caracteres através do layout ativo e combina as teclas mortas com a tecla
+* seguinte. Uma tecla pode gerar até dois caracteres, quando o acento pendente não se combina com ela.
+*/
+pub struct Composer {
+    layout: &'static dyn KeyboardLayout,
+    dead_key: Option<DeadKey>,
+}
+
+impl Composer {
+    pub const fn new(layout: &'static dyn KeyboardLayout) -> Composer {
+        Composer { layout, dead_key: None }
+    }
+
+    pub fn layout(&self) -> &'static dyn KeyboardLayout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: &'static dyn KeyboardLayout) {
+        self.layout = layout;
+        self.dead_key = None;
+    }
+
+    // Teclas soltas e combinações com Ctrl ou Alt esquerdo não produzem caracteres.
+    pub fn feed(&mut self, event: &KeyEvent) -> [Option<char>; 2] {
+        let modifiers = &event.modifiers;
+        if event.state != KeyState::Down || modifiers.ctrl() || modifiers.left_alt {
+            return [None, None];
+        }
+
+        match (self.layout.map(event.code, modifiers), self.dead_key.take()) {
+            (None, pending) => {
+                self.dead_key = pending;                                                            // Modificadores não cancelam o acento
+                [None, None]
+            }
+            (Some(DecodedKey::Dead(dead)), None) => {
+                self.dead_key = Some(dead);
+                [None, None]
+            }
+            (Some(DecodedKey::Dead(dead)), Some(pending)) if dead == pending => {
+                [Some(pending.spacing_char()), None]
+            }
+            (Some(DecodedKey::Dead(dead)), Some(pending)) => {
+                self.dead_key = Some(dead);
+                [Some(pending.spacing_char()), None]
+            }
+            (Some(DecodedKey::Char(character)), None) => [Some(character), None],
+            (Some(DecodedKey::Char(' ')), Some(pending)) => [Some(pending.spacing_char()), None],
+            (Some(DecodedKey::Char(character)), Some(pending)) => match pending.compose(character) {
+                Some(composed) => [Some(composed), None],
+                None => [Some(pending.spacing_char()), Some(character)],
+            },
+        }
+    }
+}
+
+// Evento de tecla acompanhado do caractere que ele produziu no layout ativo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub event: KeyEvent,
+    pub character: Option<char>,
+}
+
+const QUEUE_SIZE: usize = 64;
+
+// Estado do driver: decodificador, layout, fila de teclas e atualização pendente dos LEDs.
+struct Keyboard {
+    decoder: Decoder,
+    composer: Composer,
+    queue: [Option<KeyPress>; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+    pending_leds: Option<u8>,
+}
+
+impl Keyboard {
+    const fn new() -> Keyboard {
+        Keyboard {
+            decoder: Decoder::new(ScancodeSet::Set1),
+            composer: Composer::new(&keyboard_layout::US),
+            queue: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+            pending_leds: None,
+        }
+    }
+
+    // Quando a fila está cheia a tecla mais nova é descartada.
+    fn push(&mut self, key: KeyPress) {
+        if self.len < QUEUE_SIZE {
+            self.queue[(self.head + self.len) % QUEUE_SIZE] = Some(key);
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<KeyPress> {
+        if self.len == 0 {
+            return None;
+        }
+        let key = self.queue[self.head].take();
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        key
+    }
+}
+
+static KEYBOARD: Mutex<Keyboard> = Mutex::new(Keyboard::new());
+static ECHO: AtomicBool = AtomicBool::new(true);
+
+/* Consulta o byte de configuração do controlador: quando a tradução está ativa o teclado é lido no
+* set 1, senão no set 2. Também descarta bytes pendentes antes de habilitar a IRQ.
+*/
+fn detect_scancode_set() -> ScancodeSet {
+    ps2::flush();
+    match ps2::read_config() {
+        Some(config) if config & ps2::CONFIG_TRANSLATION == 0 => ScancodeSet::Set2,
+        _ => ScancodeSet::Set1,
+    }
+}
+
+pub fn init() {
+    let set = detect_scancode_set();
+    let layout = cmdline::get("keymap").and_then(keyboard_layout::by_name);
+    without_interrupts(|| {
+        let mut keyboard = KEYBOARD.lock();
+        keyboard.decoder.set_scancode_set(set);
+        if let Some(layout) = layout {
+            keyboard.composer.set_layout(layout);
+        }
+        keyboard.pending_leds = Some(keyboard.decoder.modifiers().leds());
+    });
+    interrupts::register_irq(InterruptIndex::Keyboard.irq(), keyboard_interrupt)
+        .expect("IRQ do teclado ja registrada");
+    ps2::write_data(KEYBOARD_SET_LEDS);                                                             // O ACK chega pela IRQ 1, que precisa estar habilitada
+}
+
+// Remove a próxima tecla da fila, se houver.
+pub fn read_key() -> Option<KeyPress> {
+    without_interrupts(|| KEYBOARD.lock().pop())
+}
+
+pub fn read_event() -> Option<KeyEvent> {
+    read_key().map(|key| key.event)
+}
+
+pub fn layout() -> &'static dyn KeyboardLayout {
+    without_interrupts(|| KEYBOARD.lock().composer.layout())
+}
+
+// Troca o layout em tempo de execução, descartando um acento pendente.
+pub fn set_layout(layout: &'static dyn KeyboardLayout) {
+    without_interrupts(|| KEYBOARD.lock().composer.set_layout(layout));
+}
+
+pub fn modifiers() -> Modifiers {
+    without_interrupts(|| KEYBOARD.lock().decoder.modifiers())
+}
+
+// Habilita ou desabilita a impressão automática dos caracteres digitados, retornando o estado anterior.
+pub fn set_echo(enabled: bool) -> bool {
+    ECHO.swap(enabled, Ordering::SeqCst)
+}
+
+/* Os LEDs são atualizados com o comando 0xed seguido do estado das travas. O segundo byte só pode ser
+* enviado depois que o teclado confirmar o comando com 0xfa, que chega como uma nova IRQ 1.
+*/
+fn keyboard_interrupt() {
+    let scancode = ps2::read_data();
+
+    let mut keyboard = KEYBOARD.lock();
+    match scancode {
+        DEVICE_ACK => {
+            if let Some(leds) = keyboard.pending_leds.take() {
+                ps2::write_data(leds);
+            }
+            return;
+        }
+        DEVICE_RESEND => {
+            if keyboard.pending_leds.is_some() {
+                ps2::write_data(KEYBOARD_SET_LEDS);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let leds = keyboard.decoder.modifiers().leds();
+    let event = match keyboard.decoder.feed(scancode) {
+        Some(event) => event,
+        None => return,
+    };
+
+    if event.state == KeyState::Down && event.modifiers.shift() {                                   // Shift+PageUp e Shift+PageDown rolam o histórico da tela
+        let scroll: Option<fn(usize)> = match event.code {
+            KeyCode::PageUp => Some(vga_buffer::scroll_back),
+            KeyCode::PageDown => Some(vga_buffer::scroll_forward),
+            _ => None,
+        };
+        if let Some(scroll) = scroll {
+            drop(keyboard);
+            scroll(vga_buffer::BUFFER_HEIGHT / 2);
+            return;
+        }
+    }
+    if event.state == KeyState::Down && event.modifiers.alt() {                                     // Alt+F1 a Alt+F6 trocam o console virtual exibido
+        let console = match event.code {
+            KeyCode::F1 => Some(0),
+            KeyCode::F2 => Some(1),
+            KeyCode::F3 => Some(2),
+            KeyCode::F4 => Some(3),
+            KeyCode::F5 => Some(4),
+            KeyCode::F6 => Some(5),
+            _ => None,
+        };
+        if let Some(console) = console {
+            drop(keyboard);
+            vga_buffer::switch_console(console);
+            return;
+        }
+    }
+
+    let characters = keyboard.composer.feed(&event);
+    match characters {
+        [Some(first), Some(second)] => {
+            keyboard.push(KeyPress { event, character: Some(first) });
+            keyboard.push(KeyPress { event, character: Some(second) });
+        }
+        [character, _] => keyboard.push(KeyPress { event, character }),
+    }
+
+    if event.modifiers.leds() != leds {
+        keyboard.pending_leds = Some(event.modifiers.leds());
+        ps2::write_data(KEYBOARD_SET_LEDS);
+    }
+    drop(keyboard);
+
+    if ECHO.load(Ordering::SeqCst) {
+        for &character in characters.iter().flatten() {
+            if character == '\n' || !character.is_control() {
+                vga_buffer::print_char(character);
+            }
+        }
+    }
+}
+
+#[test_case]
+fn test_decode_set1() {
+    let mut decoder = Decoder::new(ScancodeSet::Set1);
+    let event = decoder.feed(0x1e).unwrap();
+    assert_eq!((event.code, event.state), (KeyCode::A, KeyState::Down));
+    let event = decoder.feed(0x9e).unwrap();
+    assert_eq!((event.code, event.state), (KeyCode::A, KeyState::Up));
+
+    assert_eq!(decoder.feed(0xe0), None);
+    let event = decoder.feed(0x48).unwrap();
+    assert_eq!((event.code, event.state), (KeyCode::ArrowUp, KeyState::Down));
+}
+
+#[test_case]
+fn test_decode_set2() {
+    let mut decoder = Decoder::new(ScancodeSet::Set2);
+    assert_eq!(decoder.feed(0x1c).unwrap().code, KeyCode::A);
+    assert_eq!(decoder.feed(0xf0), None);
+    assert_eq!(decoder.feed(0x1c).unwrap().state, KeyState::Up);
+
+    assert_eq!(decoder.feed(0xe0), None);
+    assert_eq!(decoder.feed(0xf0), None);
+    let event = decoder.feed(0x11).unwrap();
+    assert_eq!((event.code, event.state), (KeyCode::RightAlt, KeyState::Up));
+}
+
+#[test_case]
+fn test_shift_and_caps_lock() {
+    let mut decoder = Decoder::new(ScancodeSet::Set1);
+    let mut composer = Composer::new(&keyboard_layout::US);
+    decoder.feed(0x2a);                                                                             // Shift esquerdo pressionado
+    let event = decoder.feed(0x1e).unwrap();
+    assert!(event.modifiers.shift());
+    assert_eq!(composer.feed(&event), [Some('A'), None]);
+    decoder.feed(0xaa);
+
+    decoder.feed(0x3a);
+    decoder.feed(0x3a);                                                                             // Repetição automática não alterna a trava
+    decoder.feed(0xba);
+    let event = decoder.feed(0x1e).unwrap();
+    assert!(event.modifiers.caps_lock);
+    assert_eq!(composer.feed(&event), [Some('A'), None]);
+    assert_eq!(composer.feed(&decoder.feed(0x02).unwrap()), [Some('1'), None]);
+}
+
+#[test_case]
+fn test_dead_key_composition() {
+    let mut decoder = Decoder::new(ScancodeSet::Set1);
+    let mut composer = Composer::new(&keyboard_layout::ABNT2);
+    let mut type_key = |scancode: u8| {
+        let characters = composer.feed(&decoder.feed(scancode).unwrap());
+        decoder.feed(scancode | 0x80);
+        characters
+    };
+
+    assert_eq!(type_key(0x28), [None, None]);                                                       // "~"
+    assert_eq!(type_key(0x1e), [Some('ã'), None]);
+    assert_eq!(type_key(0x1a), [None, None]);                                                       // "´"
+    assert_eq!(type_key(0x39), [Some('´'), None]);
+    assert_eq!(type_key(0x1a), [None, None]);
+    assert_eq!(type_key(0x2d), [Some('´'), Some('x')]);
+    assert_eq!(type_key(0x27), [Some('ç'), None]);
+}
@@ -0,0 +1,271 @@
+use crate::keyboard::{KeyCode, Modifiers};
+
+/* O layout define qual caractere cada tecla produz. Os códigos de tecla (KeyCode) seguem a posição
+* física no teclado americano, então em outros layouts a mesma tecla gera caracteres diferentes, por
+* exemplo KeyCode::Semicolon é o "ç" no ABNT2.
+*
+* Teclas mortas (dead keys) não produzem caractere sozinhas: o acento é combinado com a próxima tecla
+* ("´" seguido de "a" gera "á"). Quando a combinação não existe, o acento e a tecla são emitidos
+* separadamente, e o acento seguido de espaço produz o próprio acento.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadKey {
+    Acute,
+    Grave,
+    Tilde,
+    Circumflex,
+    Diaeresis,
+}
+
+impl DeadKey {
+    pub fn spacing_char(self) -> char {
+        match self {
+            DeadKey::Acute => '´',
+            DeadKey::Grave => '`',
+            DeadKey::Tilde => '~',
+            DeadKey::Circumflex => '^',
+            DeadKey::Diaeresis => '¨',
+        }
+    }
+
+    // Combina o acento com uma letra, limitado aos caracteres do Latin-1 (U+0000 a U+00FF).
+    pub fn compose(self, base: char) -> Option<char> {
+        let lower = base.to_ascii_lowercase();
+        let composed = match (self, lower) {
+            (DeadKey::Acute, 'a') => 'á',
+            (DeadKey::Acute, 'e') => 'é',
+            (DeadKey::Acute, 'i') => 'í',
+            (DeadKey::Acute, 'o') => 'ó',
+            (DeadKey::Acute, 'u') => 'ú',
+            (DeadKey::Acute, 'y') => 'ý',
+            (DeadKey::Acute, 'c') => 'ç',                                                           // Convenção do ABNT2 para o "ç" em teclados sem a tecla
+            (DeadKey::Grave, 'a') => 'à',
+            (DeadKey::Grave, 'e') => 'è',
+            (DeadKey::Grave, 'i') => 'ì',
+            (DeadKey::Grave, 'o') => 'ò',
+            (DeadKey::Grave, 'u') => 'ù',
+            (DeadKey::Tilde, 'a') => 'ã',
+            (DeadKey::Tilde, 'o') => 'õ',
+            (DeadKey::Tilde, 'n') => 'ñ',
+            (DeadKey::Circumflex, 'a') => 'â',
+            (DeadKey::Circumflex, 'e') => 'ê',
+            (DeadKey::Circumflex, 'i') => 'î',
+            (DeadKey::Circumflex, 'o') => 'ô',
+            (DeadKey::Circumflex, 'u') => 'û',
+            (DeadKey::Diaeresis, 'a') => 'ä',
+            (DeadKey::Diaeresis, 'e') => 'ë',
+            (DeadKey::Diaeresis, 'i') => 'ï',
+            (DeadKey::Diaeresis, 'o') => 'ö',
+            (DeadKey::Diaeresis, 'u') => 'ü',
+            (DeadKey::Diaeresis, 'y') => 'ÿ',
+            _ => return None,
+        };
+
+        if base.is_ascii_uppercase() {
+            composed.to_uppercase().next().filter(|upper| u32::from(*upper) <= 0xff)                // "Ÿ" não faz parte do Latin-1
+        } else {
+            Some(composed)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedKey {
+    Char(char),
+    Dead(DeadKey),
+}
+
+pub trait KeyboardLayout: Sync {
+    fn name(&self) -> &'static str;
+
+    /* Retorna o que a tecla produz com os modificadores informados. Ctrl e Alt esquerdo já foram
+    * filtrados pelo driver, mas o Alt direito (AltGr) é repassado ao layout.
+    */
+    fn map(&self, code: KeyCode, modifiers: &Modifiers) -> Option<DecodedKey>;
+}
+
+// Letras, espaço, Enter e teclado numérico, que são iguais em todos os layouts suportados.
+fn common(code: KeyCode, modifiers: &Modifiers) -> Option<char> {
+    use KeyCode::*;
+
+    let letter = |lower: char| {
+        if modifiers.shift() != modifiers.caps_lock { lower.to_ascii_uppercase() } else { lower }
+    };
+    let keypad = |digit: char| if modifiers.num_lock { Some(digit) } else { None };
+
+    let character = match code {
+        A => letter('a'), B => letter('b'), C => letter('c'), D => letter('d'), E => letter('e'),
+        F => letter('f'), G => letter('g'), H => letter('h'), I => letter('i'), J => letter('j'),
+        K => letter('k'), L => letter('l'), M => letter('m'), N => letter('n'), O => letter('o'),
+        P => letter('p'), Q => letter('q'), R => letter('r'), S => letter('s'), T => letter('t'),
+        U => letter('u'), V => letter('v'), W => letter('w'), X => letter('x'), Y => letter('y'),
+        Z => letter('z'),
+        Space => ' ', Tab => '\t', Enter | KeypadEnter => '\n', Backspace => '\u{8}',
+        KeypadSlash => '/', KeypadMultiply => '*', KeypadMinus => '-', KeypadPlus => '+',
+        Keypad0 => return keypad('0'), Keypad1 => return keypad('1'), Keypad2 => return keypad('2'),
+        Keypad3 => return keypad('3'), Keypad4 => return keypad('4'), Keypad5 => return keypad('5'),
+        Keypad6 => return keypad('6'), Keypad7 => return keypad('7'), Keypad8 => return keypad('8'),
+        Keypad9 => return keypad('9'), KeypadPeriod => return keypad('.'),
+        _ => return None,
+    };
+    Some(character)
+}
+
+fn shifted(modifiers: &Modifiers, normal: char, shift: char) -> Option<DecodedKey> {
+    Some(DecodedKey::Char(if modifiers.shift() { shift } else { normal }))
+}
+
+// Layout americano (US), sem teclas mortas e sem AltGr.
+pub struct Us;
+
+impl KeyboardLayout for Us {
+    fn name(&self) -> &'static str {
+        "us"
+    }
+
+    fn map(&self, code: KeyCode, modifiers: &Modifiers) -> Option<DecodedKey> {
+        use KeyCode::*;
+
+        if modifiers.right_alt {
+            return None;
+        }
+        let m = modifiers;
+        match code {
+            Key1 => shifted(m, '1', '!'), Key2 => shifted(m, '2', '@'), Key3 => shifted(m, '3', '#'),
+            Key4 => shifted(m, '4', '$'), Key5 => shifted(m, '5', '%'), Key6 => shifted(m, '6', '^'),
+            Key7 => shifted(m, '7', '&'), Key8 => shifted(m, '8', '*'), Key9 => shifted(m, '9', '('),
+            Key0 => shifted(m, '0', ')'), Minus => shifted(m, '-', '_'), Equals => shifted(m, '=', '+'),
+            Backtick => shifted(m, '`', '~'), BracketLeft => shifted(m, '[', '{'),
+            BracketRight => shifted(m, ']', '}'), Backslash => shifted(m, '\\', '|'),
+            NonUsBackslash => shifted(m, '\\', '|'), Semicolon => shifted(m, ';', ':'),
+            Quote => shifted(m, '\'', '"'), Comma => shifted(m, ',', '<'),
+            Period => shifted(m, '.', '>'), Slash => shifted(m, '/', '?'),
+            _ => common(code, m).map(DecodedKey::Char),
+        }
+    }
+}
+
+/* Layout britânico (UK). Difere do americano no Shift+2 e Shift+3, na tecla "#~" (que ocupa a posição
+* do "\" americano) e na tecla extra "\|" ao lado do Shift esquerdo.
+*/
+pub struct Uk;
+
+impl KeyboardLayout for Uk {
+    fn name(&self) -> &'static str {
+        "uk"
+    }
+
+    fn map(&self, code: KeyCode, modifiers: &Modifiers) -> Option<DecodedKey> {
+        use KeyCode::*;
+
+        let m = modifiers;
+        if m.right_alt {
+            return match code {
+                Backtick => Some(DecodedKey::Char('¦')),
+                A => Some(DecodedKey::Char(if m.shift() { 'Á' } else { 'á' })),
+                E => Some(DecodedKey::Char(if m.shift() { 'É' } else { 'é' })),
+                I => Some(DecodedKey::Char(if m.shift() { 'Í' } else { 'í' })),
+                O => Some(DecodedKey::Char(if m.shift() { 'Ó' } else { 'ó' })),
+                U => Some(DecodedKey::Char(if m.shift() { 'Ú' } else { 'ú' })),
+                _ => None,
+            };
+        }
+        match code {
+            Key1 => shifted(m, '1', '!'), Key2 => shifted(m, '2', '"'), Key3 => shifted(m, '3', '£'),
+            Key4 => shifted(m, '4', '$'), Key5 => shifted(m, '5', '%'), Key6 => shifted(m, '6', '^'),
+            Key7 => shifted(m, '7', '&'), Key8 => shifted(m, '8', '*'), Key9 => shifted(m, '9', '('),
+            Key0 => shifted(m, '0', ')'), Minus => shifted(m, '-', '_'), Equals => shifted(m, '=', '+'),
+            Backtick => shifted(m, '`', '¬'), BracketLeft => shifted(m, '[', '{'),
+            BracketRight => shifted(m, ']', '}'), Backslash => shifted(m, '#', '~'),
+            NonUsBackslash => shifted(m, '\\', '|'), Semicolon => shifted(m, ';', ':'),
+            Quote => shifted(m, '\'', '@'), Comma => shifted(m, ',', '<'),
+            Period => shifted(m, '.', '>'), Slash => shifted(m, '/', '?'),
+            _ => common(code, m).map(DecodedKey::Char),
+        }
+    }
+}
+
+/* Layout brasileiro ABNT2. Possui as teclas "ç", "/?" ao lado do Shift direito (International1) e
+* "." no teclado numérico (KeypadComma), cuja tecla Delete produz a vírgula decimal. Os acentos
+* ficam nas teclas mortas "´`", "~^" e Shift+6 ("¨").
+*/
+pub struct Abnt2;
+
+impl KeyboardLayout for Abnt2 {
+    fn name(&self) -> &'static str {
+        "abnt2"
+    }
+
+    fn map(&self, code: KeyCode, modifiers: &Modifiers) -> Option<DecodedKey> {
+        use KeyCode::*;
+
+        let m = modifiers;
+        let dead = |normal: DeadKey, shift: DeadKey| {
+            Some(DecodedKey::Dead(if m.shift() { shift } else { normal }))
+        };
+
+        if m.right_alt {
+            let character = match code {
+                Key1 => '¹', Key2 => '²', Key3 => '³', Key4 => '£', Key5 => '¢', Key6 => '¬',
+                Equals => '§', Q => '/', W => '?', E => '°', BracketRight => 'ª', Backslash => 'º',
+                International1 => '°',
+                _ => return None,
+            };
+            return Some(DecodedKey::Char(character));
+        }
+        match code {
+            Key1 => shifted(m, '1', '!'), Key2 => shifted(m, '2', '@'), Key3 => shifted(m, '3', '#'),
+            Key4 => shifted(m, '4', '$'), Key5 => shifted(m, '5', '%'), Key7 => shifted(m, '7', '&'),
+            Key8 => shifted(m, '8', '*'), Key9 => shifted(m, '9', '('), Key0 => shifted(m, '0', ')'),
+            Key6 if m.shift() => Some(DecodedKey::Dead(DeadKey::Diaeresis)),
+            Key6 => Some(DecodedKey::Char('6')),
+            Minus => shifted(m, '-', '_'), Equals => shifted(m, '=', '+'),
+            Backtick => shifted(m, '\'', '"'), BracketLeft => dead(DeadKey::Acute, DeadKey::Grave),
+            BracketRight => shifted(m, '[', '{'), Backslash => shifted(m, ']', '}'),
+            Semicolon => Some(DecodedKey::Char(if m.shift() != m.caps_lock { 'Ç' } else { 'ç' })),
+            Quote => dead(DeadKey::Tilde, DeadKey::Circumflex),
+            NonUsBackslash => shifted(m, '\\', '|'), Comma => shifted(m, ',', '<'),
+            Period => shifted(m, '.', '>'), Slash => shifted(m, ';', ':'),
+            International1 => shifted(m, '/', '?'), KeypadComma => Some(DecodedKey::Char('.')),
+            KeypadPeriod if m.num_lock => Some(DecodedKey::Char(',')),
+            _ => common(code, m).map(DecodedKey::Char),
+        }
+    }
+}
+
+pub static US: Us = Us;
+pub static UK: Uk = Uk;
+pub static ABNT2: Abnt2 = Abnt2;
+
+static LAYOUTS: [&dyn KeyboardLayout; 3] = [&US, &ABNT2, &UK];
+
+pub fn layouts() -> &'static [&'static dyn KeyboardLayout] {
+    &LAYOUTS
+}
+
+pub fn by_name(name: &str) -> Option<&'static dyn KeyboardLayout> {
+    LAYOUTS.iter().copied().find(|layout| layout.name() == name)
+}
+
+#[test_case]
+fn test_compose_dead_keys() {
+    assert_eq!(DeadKey::Acute.compose('a'), Some('á'));
+    assert_eq!(DeadKey::Tilde.compose('O'), Some('Õ'));
+    assert_eq!(DeadKey::Circumflex.compose('e'), Some('ê'));
+    assert_eq!(DeadKey::Diaeresis.compose('Y'), None);
+    assert_eq!(DeadKey::Grave.compose('x'), None);
+}
+
+#[test_case]
+fn test_abnt2_layout() {
+    let mut modifiers = Modifiers::new();
+    assert_eq!(ABNT2.map(KeyCode::Semicolon, &modifiers), Some(DecodedKey::Char('ç')));
+    assert_eq!(ABNT2.map(KeyCode::Quote, &modifiers), Some(DecodedKey::Dead(DeadKey::Tilde)));
+    assert_eq!(ABNT2.map(KeyCode::International1, &modifiers), Some(DecodedKey::Char('/')));
+
+    modifiers.left_shift = true;
+    assert_eq!(ABNT2.map(KeyCode::Key6, &modifiers), Some(DecodedKey::Dead(DeadKey::Diaeresis)));
+    assert_eq!(ABNT2.map(KeyCode::BracketLeft, &modifiers), Some(DecodedKey::Dead(DeadKey::Grave)));
+    assert_eq!(UK.map(KeyCode::Key3, &modifiers), Some(DecodedKey::Char('£')));
+    assert_eq!(US.map(KeyCode::Key3, &modifiers), Some(DecodedKey::Char('#')));
+}
@@ -26,11 +26,61 @@
 pub mod serial;
 pub mod vga_buffer;
 pub mod interrupts;
+pub mod gdt;
+pub mod memory;
+pub mod acpi;
+pub mod apic;
+pub mod cmdline;
+pub mod pit;
+pub mod time;
+pub mod clock;
+pub mod rtc;
+pub mod hpet;
+pub mod keyboard;
+pub mod keyboard_layout;
+pub mod ps2;
+pub mod mouse;
+pub mod console;
+pub mod shell;
+pub mod pci;
+pub mod font;
+pub mod framebuffer;
+pub mod graphics;
+pub mod status_bar;
 
 use core::panic::PanicInfo;
+use bootloader::BootInfo;
+#[cfg(test)]
+use bootloader::entry_point;
 
-pub fn init() {
+pub fn init(boot_info: &'static BootInfo) {
+    vga_buffer::init();
+    gdt::init();
     interrupts::init_idt();
+    memory::init(boot_info);
+    if let Err(error) = framebuffer::init() {
+        println!("Framebuffer indisponivel ({:?})", error);
+    }
+    interrupts::init_controller();
+    hpet::init();
+    time::init();
+    status_bar::init();
+    clock::init();
+    if let Err(error) = mouse::init() {
+        println!("Mouse PS/2 indisponivel ({:?})", error);
+    }
+    keyboard::init();
+    serial::init().expect("IRQ da serial ja registrada");
+    x86_64::instructions::interrupts::enable();                                                     // Executa a instrução sti para habilitar as interrupções externas.
+}
+
+/* Em vez de um loop vazio que consome 100% da CPU, a instrução hlt para a CPU até a chegada da
+* próxima interrupção.
+*/
+pub fn hlt_loop() -> ! {
+    loop {
+        x86_64::instructions::hlt();
+    }
 }
 
 pub trait Testable {
@@ -45,8 +95,13 @@ where T: Fn(),
          * Após imprimir o nome da função, a chamamos através do self.
          */
         serial_print!("{}...\t", core::any::type_name::<T>());
+        let start = clock::Instant::now();
         self();
-        serial_println!("[ok]");
+        if clock::is_precise() {
+            serial_println!("[ok] ({} ns)", start.elapsed_nanos());                                // Duração medida com o TSC ou o HPET
+        } else {
+            serial_println!("[ok]");
+        }
     }
 }
 
@@ -67,7 +122,7 @@ pub fn test_panic_handler(info: &PanicInfo) -> ! {
     serial_println!("[failed]\n");
     serial_println!("Error: {}\n", info);
     exit_qemu(QemuExitCode::Failed);
-    loop {}
+    hlt_loop();
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -92,13 +147,15 @@ pub fn exit_qemu(exit_code: QemuExitCode) {
 
 
 
+#[cfg(test)]
+entry_point!(test_kernel_main);
+
 /// Entry point for `cargo test`
 #[cfg(test)]
-#[no_mangle]
-pub extern "C" fn _start() -> ! {                                                                   // A lib é testada fora do main, logo precisa de um ponto de entrada e um manipulador de pânico.
-    init();
+fn test_kernel_main(boot_info: &'static BootInfo) -> ! {                                           // A lib é testada fora do main, logo precisa de um ponto de entrada e um manipulador de pânico.
+    init(boot_info);
     test_main();
-    loop {}
+    hlt_loop();
 }
 
 #[cfg(test)]
@@ -29,9 +29,9 @@
 #![reexport_test_harness_main = "test_main"]
 
 
-mod vga_buffer;
-
 use core::panic::PanicInfo;
+use bootloader::{BootInfo, entry_point};
+use rust_os::println;                                                                               // Utiliza o mesmo WRITER da biblioteca, compartilhado com o eco do teclado
 
 //static HELLO: &[u8] = b"Hello World!";
 
@@ -45,9 +45,13 @@ use core::panic::PanicInfo;
 * Isso é necessário porque o ponto de entrada não é chamado por nenhuma função, mas invocado diretamente
 * pelo sistema operacional ou bootloader. Então, em vez de retornar, o ponto de entrada deve, por exemplo,
 * invocar a exit do sistema operacional (reiniciar a máquina, por exemplo).
+* O bootloader passa para o ponto de entrada uma referência para o BootInfo, que contém o mapa de
+* memória e o deslocamento em que a memória física foi mapeada. A macro entry_point! define a função
+* _start com essas características e verifica em tempo de compilação a assinatura de kernel_main.
 */
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+entry_point!(kernel_main);
+
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
     /*let vga_buffer = 0xb8000 as *mut u8;
 
     for( i, &byte) in HELLO.iter().enumerate() {
@@ -67,14 +71,15 @@ pub extern "C" fn _start() -> ! {
     println!("Hello World! \n{}", 95);
     println!("\nTeste");
 
-    rust_os::init();
+    rust_os::init(boot_info);
+    println!("Data: {}", rust_os::rtc::now());
     x86_64::instructions::interrupts::int3();                                                       // Chama breakpoint exception
 
     #[cfg(test)]
     test_main();
 
     println!("\nNao crashou!");
-    loop {}
+    rust_os::shell::run();                                                                          // O shell aceita comandos pelo teclado e pela serial
 }
 
 
@@ -83,7 +88,7 @@ pub extern "C" fn _start() -> ! {
 #[panic_handler]                                                                                    // Define a função que o compilador deve invocar quando um panic acontece.
 fn panic(info: &PanicInfo) -> ! {
     println!("{}", info);
-    loop {}
+    rust_os::hlt_loop();
 }
 
 #[cfg(test)]
@@ -0,0 +1,53 @@
+use bootloader::BootInfo;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::{PhysAddr, VirtAddr};
+
+/* Com a feature map_physical_memory o bootloader mapeia toda a memória física em um intervalo de
+* endereços virtuais a partir de physical_memory_offset. Assim conseguimos acessar tabelas ACPI e
+* registradores mapeados em memória (MMIO) apenas somando o deslocamento ao endereço físico.
+*/
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+// Mapa de memória física fornecido pelo bootloader, com o tipo de cada região.
+static MEMORY_MAP: Mutex<Option<&'static MemoryMap>> = Mutex::new(None);
+
+pub fn init(boot_info: &'static BootInfo) {
+    PHYSICAL_MEMORY_OFFSET.store(boot_info.physical_memory_offset, Ordering::SeqCst);
+    *MEMORY_MAP.lock() = Some(&boot_info.memory_map);
+}
+
+pub fn memory_map() -> Option<&'static MemoryMap> {
+    *MEMORY_MAP.lock()
+}
+
+// Soma o tamanho das regiões de um tipo, em bytes.
+pub fn total_of(region_type: MemoryRegionType) -> u64 {
+    memory_map().map_or(0, |map| {
+        map.iter()
+            .filter(|region| region.region_type == region_type)
+            .map(|region| region.range.end_addr() - region.range.start_addr())
+            .sum()
+    })
+}
+
+// Memória livre para uso do kernel, enquanto não existe um alocador de frames.
+pub fn usable_memory() -> u64 {
+    total_of(MemoryRegionType::Usable)
+}
+
+pub fn physical_memory_offset() -> VirtAddr {
+    VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::SeqCst))
+}
+
+// Converte um endereço físico para o endereço virtual em que ele está mapeado.
+pub fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
+    physical_memory_offset() + addr.as_u64()
+}
+
+#[test_case]
+fn test_memory_map_has_usable_memory() {
+    assert!(memory_map().is_some());
+    assert!(usable_memory() > 0);
+}
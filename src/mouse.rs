@@ -0,0 +1,302 @@
+use crate::interrupts::{self, InterruptIndex, IrqError};
+use crate::ps2::{self, DEVICE_ACK};
+use crate::vga_buffer;
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/* O mouse PS/2 fica na porta auxiliar do controlador e envia pacotes de 3 bytes a cada movimento ou
+* clique: o primeiro contém os botões e os bits de sinal, e os outros dois o deslocamento em X e Y.
+* O IntelliMouse adiciona um quarto byte com a roda (e os botões 4 e 5 no modelo de 5 botões), e é
+* habilitado enviando a sequência mágica de taxas de amostragem 200, 100, 80.
+*/
+const MOUSE_SET_DEFAULTS: u8 = 0xf6;
+const MOUSE_ENABLE_REPORTING: u8 = 0xf4;
+const MOUSE_SET_SAMPLE_RATE: u8 = 0xf3;
+const MOUSE_GET_ID: u8 = 0xf2;
+
+const ID_STANDARD: u8 = 0;
+const ID_WHEEL: u8 = 3;
+const ID_FIVE_BUTTONS: u8 = 4;
+
+const PACKET_LEFT: u8 = 1 << 0;
+const PACKET_RIGHT: u8 = 1 << 1;
+const PACKET_MIDDLE: u8 = 1 << 2;
+const PACKET_ALWAYS_ONE: u8 = 1 << 3;
+const PACKET_X_SIGN: u8 = 1 << 4;
+const PACKET_Y_SIGN: u8 = 1 << 5;
+const PACKET_X_OVERFLOW: u8 = 1 << 6;
+const PACKET_Y_OVERFLOW: u8 = 1 << 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseError {
+    NoDevice,                                                                                       // O controlador ou o mouse não responderam
+    Irq(IrqError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseButtons {
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+    pub fourth: bool,
+    pub fifth: bool,
+}
+
+// Deslocamento desde o último pacote, com Y positivo para cima e a roda positiva para baixo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseEvent {
+    pub dx: i16,
+    pub dy: i16,
+    pub wheel: i8,
+    pub buttons: MouseButtons,
+}
+
+// Monta os pacotes byte a byte, descartando bytes até encontrar um início de pacote válido.
+pub struct PacketDecoder {
+    packet: [u8; 4],
+    index: usize,
+    id: u8,
+}
+
+impl PacketDecoder {
+    pub const fn new(id: u8) -> PacketDecoder {
+        PacketDecoder { packet: [0; 4], index: 0, id }
+    }
+
+    pub fn packet_size(&self) -> usize {
+        if self.id == ID_STANDARD { 3 } else { 4 }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        if self.index == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+            return None;                                                                            // Fora de sincronia
+        }
+        self.packet[self.index] = byte;
+        self.index += 1;
+        if self.index < self.packet_size() {
+            return None;
+        }
+        self.index = 0;
+        Some(self.decode())
+    }
+
+    fn decode(&self) -> MouseEvent {
+        let flags = self.packet[0];
+        let axis = |value: u8, sign: u8, overflow: u8| {
+            if flags & overflow != 0 {
+                0                                                                                   // Valor inválido quando o contador estoura
+            } else if flags & sign != 0 {
+                i16::from(value) - 256
+            } else {
+                i16::from(value)
+            }
+        };
+
+        let extra = self.packet[3];
+        let (wheel, fourth, fifth) = match self.id {
+            ID_WHEEL => (extra as i8, false, false),
+            ID_FIVE_BUTTONS => (((extra << 4) as i8) >> 4, extra & 0x10 != 0, extra & 0x20 != 0),    // A roda ocupa apenas os 4 bits menos significativos
+            _ => (0, false, false),
+        };
+
+        MouseEvent {
+            dx: axis(self.packet[1], PACKET_X_SIGN, PACKET_X_OVERFLOW),
+            dy: axis(self.packet[2], PACKET_Y_SIGN, PACKET_Y_OVERFLOW),
+            wheel,
+            buttons: MouseButtons {
+                left: flags & PACKET_LEFT != 0,
+                right: flags & PACKET_RIGHT != 0,
+                middle: flags & PACKET_MIDDLE != 0,
+                fourth,
+                fifth,
+            },
+        }
+    }
+}
+
+/* A posição do ponteiro é mantida em uma grade de 8x16 unidades por célula, o tamanho da fonte do
+* modo texto, para que o ponteiro não salte uma célula inteira a cada unidade de movimento.
+*/
+const CELL_WIDTH: i32 = 8;
+const CELL_HEIGHT: i32 = 16;
+
+const QUEUE_SIZE: usize = 64;
+
+struct Mouse {
+    decoder: PacketDecoder,
+    x: i32,
+    y: i32,
+    drawn_at: Option<(usize, usize)>,                                                               // Célula em que o ponteiro foi desenhado
+    queue: [Option<MouseEvent>; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl Mouse {
+    const fn new() -> Mouse {
+        Mouse {
+            decoder: PacketDecoder::new(ID_STANDARD),
+            x: 0,
+            y: 0,
+            drawn_at: None,
+            queue: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: MouseEvent) {
+        if self.len < QUEUE_SIZE {
+            self.queue[(self.head + self.len) % QUEUE_SIZE] = Some(event);
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<MouseEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.queue[self.head].take();
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        event
+    }
+
+    fn cell(&self) -> (usize, usize) {
+        ((self.y / CELL_HEIGHT) as usize, (self.x / CELL_WIDTH) as usize)
+    }
+
+    fn move_by(&mut self, dx: i16, dy: i16) {
+        let (rows, cols) = (vga_buffer::BUFFER_HEIGHT as i32, vga_buffer::BUFFER_WIDTH as i32);
+        self.x = (self.x + i32::from(dx)).clamp(0, cols * CELL_WIDTH - 1);
+        self.y = (self.y - i32::from(dy)).clamp(0, rows * CELL_HEIGHT - 1);                         // O eixo Y do mouse cresce para cima
+    }
+
+    // Move o ponteiro para a célula sob a nova posição, restaurando a célula anterior.
+    fn redraw_pointer(&mut self, visible: bool) {
+        let target = if visible { Some(self.cell()) } else { None };
+        if target == self.drawn_at {
+            return;
+        }
+        vga_buffer::set_pointer(target);
+        self.drawn_at = target;
+    }
+}
+
+static MOUSE: Mutex<Mouse> = Mutex::new(Mouse::new());
+static POINTER_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+// Envia um comando ao mouse e espera a confirmação.
+fn send(command: u8) -> Result<(), MouseError> {
+    ps2::write_aux(command);
+    match ps2::poll_data() {
+        Some(DEVICE_ACK) => Ok(()),
+        _ => Err(MouseError::NoDevice),
+    }
+}
+
+fn set_sample_rate(rate: u8) -> Result<(), MouseError> {
+    send(MOUSE_SET_SAMPLE_RATE)?;
+    send(rate)
+}
+
+fn device_id() -> Result<u8, MouseError> {
+    send(MOUSE_GET_ID)?;
+    ps2::poll_data().ok_or(MouseError::NoDevice)
+}
+
+/* Tenta ativar a roda e depois os botões 4 e 5. O mouse só muda de identificador se reconhecer a
+* sequência, então um mouse comum continua enviando pacotes de 3 bytes.
+*/
+fn enable_extensions() -> Result<u8, MouseError> {
+    for rate in [200, 100, 80] {
+        set_sample_rate(rate)?;
+    }
+    if device_id()? != ID_WHEEL {
+        return Ok(ID_STANDARD);
+    }
+    for rate in [200, 200, 80] {
+        set_sample_rate(rate)?;
+    }
+    match device_id()? {
+        ID_FIVE_BUTTONS => Ok(ID_FIVE_BUTTONS),
+        _ => Ok(ID_WHEEL),
+    }
+}
+
+/* Habilita a porta auxiliar e a IRQ 12 no byte de configuração do controlador, restaura os padrões
+* do mouse, detecta o IntelliMouse e habilita o envio de pacotes. Deve ser chamada antes de habilitar
+* as interrupções e antes do teclado, pois as respostas do mouse são lidas por polling.
+*/
+pub fn init() -> Result<(), MouseError> {
+    ps2::flush();
+    ps2::write_command(ps2::COMMAND_ENABLE_AUX);
+    let config = ps2::read_config().ok_or(MouseError::NoDevice)?;
+    ps2::write_config((config | ps2::CONFIG_AUX_IRQ) & !ps2::CONFIG_AUX_CLOCK_DISABLED);
+
+    send(MOUSE_SET_DEFAULTS)?;
+    let id = enable_extensions()?;
+    send(MOUSE_ENABLE_REPORTING)?;
+
+    without_interrupts(|| MOUSE.lock().decoder = PacketDecoder::new(id));
+    interrupts::register_irq(InterruptIndex::Mouse.irq(), mouse_interrupt).map_err(MouseError::Irq)
+}
+
+// Remove o próximo evento da fila, se houver.
+pub fn read_event() -> Option<MouseEvent> {
+    without_interrupts(|| MOUSE.lock().pop())
+}
+
+// Posição do ponteiro em células do modo texto (linha, coluna).
+pub fn position() -> (usize, usize) {
+    without_interrupts(|| MOUSE.lock().cell())
+}
+
+pub fn has_wheel() -> bool {
+    without_interrupts(|| MOUSE.lock().decoder.packet_size() == 4)
+}
+
+/* Exibe ou esconde o ponteiro no modo texto. O ponteiro inverte as cores da célula, e o console o
+* esconde enquanto altera a tela e o redesenha em seguida na mesma célula.
+*/
+pub fn set_pointer_visible(visible: bool) {
+    POINTER_VISIBLE.store(visible, Ordering::SeqCst);
+    without_interrupts(|| MOUSE.lock().redraw_pointer(visible));
+}
+
+fn mouse_interrupt() {
+    let byte = ps2::read_data();
+
+    let mut mouse = MOUSE.lock();
+    if let Some(event) = mouse.decoder.feed(byte) {
+        mouse.move_by(event.dx, event.dy);
+        mouse.push(event);
+        mouse.redraw_pointer(POINTER_VISIBLE.load(Ordering::SeqCst));
+    }
+}
+
+#[test_case]
+fn test_decode_standard_packet() {
+    let mut decoder = PacketDecoder::new(ID_STANDARD);
+    assert_eq!(decoder.feed(0x00), None);                                                           // Sem o bit 3 o byte é descartado
+    assert_eq!(decoder.feed(PACKET_ALWAYS_ONE | PACKET_LEFT | PACKET_Y_SIGN), None);
+    assert_eq!(decoder.feed(5), None);
+    let event = decoder.feed(0xfe).unwrap();
+    assert_eq!((event.dx, event.dy, event.wheel), (5, -2, 0));
+    assert!(event.buttons.left && !event.buttons.right);
+}
+
+#[test_case]
+fn test_decode_intellimouse_packet() {
+    let mut decoder = PacketDecoder::new(ID_WHEEL);
+    let packet = [PACKET_ALWAYS_ONE | PACKET_X_SIGN, 0xff, 0, 0xff];
+    let event = packet.iter().filter_map(|&byte| decoder.feed(byte)).last().unwrap();
+    assert_eq!((event.dx, event.dy, event.wheel), (-1, 0, -1));
+
+    let mut decoder = PacketDecoder::new(ID_FIVE_BUTTONS);
+    let packet = [PACKET_ALWAYS_ONE, 0, 0, 0x1f];
+    let event = packet.iter().filter_map(|&byte| decoder.feed(byte)).last().unwrap();
+    assert_eq!(event.wheel, -1);
+    assert!(event.buttons.fourth && !event.buttons.fifth);
+}
@@ -0,0 +1,95 @@
+use x86_64::instructions::port::Port;
+
+/* O espaço de configuração PCI é acessado pelo mecanismo 1: o endereço do registrador (barramento,
+* dispositivo, função e deslocamento) é escrito na porta 0xcf8 e o valor é lido ou escrito na porta
+* 0xcfc. Os registradores têm 32 bits, então o deslocamento precisa ser múltiplo de 4.
+*/
+const CONFIG_ADDRESS_PORT: u16 = 0xcf8;
+const CONFIG_DATA_PORT: u16 = 0xcfc;
+const CONFIG_ENABLE: u32 = 1 << 31;
+
+const REG_ID: u8 = 0x00;                                                                            // Fabricante nos 16 bits baixos e dispositivo nos altos
+const REG_HEADER_TYPE: u8 = 0x0c;
+const REG_BAR0: u8 = 0x10;
+
+const VENDOR_NONE: u16 = 0xffff;                                                                    // Lido quando não há dispositivo no endereço
+const HEADER_MULTI_FUNCTION: u32 = 1 << 23;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    pub fn new(bus: u8, device: u8, function: u8) -> PciAddress {
+        PciAddress { bus, device, function }
+    }
+
+    fn config_address(&self, offset: u8) -> u32 {
+        CONFIG_ENABLE
+            | u32::from(self.bus) << 16
+            | u32::from(self.device & 0x1f) << 11
+            | u32::from(self.function & 0x07) << 8
+            | u32::from(offset & 0xfc)
+    }
+
+    pub fn read(&self, offset: u8) -> u32 {
+        unsafe {
+            Port::new(CONFIG_ADDRESS_PORT).write(self.config_address(offset));
+            Port::new(CONFIG_DATA_PORT).read()
+        }
+    }
+
+    pub fn write(&self, offset: u8, value: u32) {
+        unsafe {
+            Port::new(CONFIG_ADDRESS_PORT).write(self.config_address(offset));
+            Port::new(CONFIG_DATA_PORT).write(value);
+        }
+    }
+
+    pub fn vendor_id(&self) -> u16 {
+        self.read(REG_ID) as u16
+    }
+
+    pub fn device_id(&self) -> u16 {
+        (self.read(REG_ID) >> 16) as u16
+    }
+
+    // Valor bruto de um dos seis BARs (Base Address Registers), incluindo os bits de tipo.
+    pub fn bar(&self, index: u8) -> u32 {
+        self.read(REG_BAR0 + 4 * index)
+    }
+
+    fn is_multi_function(&self) -> bool {
+        self.read(REG_HEADER_TYPE) & HEADER_MULTI_FUNCTION != 0
+    }
+}
+
+/* Procura um dispositivo pelo fabricante e modelo percorrendo todos os barramentos. As funções além
+* da 0 só são verificadas quando o dispositivo se declara multifunção.
+*/
+pub fn find_device(vendor_id: u16, device_id: u16) -> Option<PciAddress> {
+    for bus in 0..=255 {
+        for device in 0..32 {
+            let first = PciAddress::new(bus, device, 0);
+            if first.vendor_id() == VENDOR_NONE {
+                continue;
+            }
+            let functions = if first.is_multi_function() { 8 } else { 1 };
+            for function in 0..functions {
+                let address = PciAddress::new(bus, device, function);
+                if address.vendor_id() == vendor_id && address.device_id() == device_id {
+                    return Some(address);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[test_case]
+fn test_host_bridge_present() {
+    assert_ne!(PciAddress::new(0, 0, 0).vendor_id(), VENDOR_NONE);
+}
@@ -0,0 +1,110 @@
+use crate::time::Timer;
+use core::time::Duration;
+use x86_64::instructions::port::Port;
+
+/* O PIT (Programmable Interval Timer) 8253/8254 possui um oscilador de aproximadamente 1,193182 MHz
+* e três canais. O canal 0 está ligado na IRQ 0 e gera uma interrupção sempre que o seu contador,
+* decrementado a cada pulso do oscilador, chega a zero.
+*/
+pub const BASE_FREQUENCY_HZ: u32 = 1_193_182;
+
+const CHANNEL0_PORT: u16 = 0x40;
+const COMMAND_PORT: u16 = 0x43;
+
+/* Comando 0x36: canal 0, acesso ao byte baixo e depois ao alto, modo 3 (gerador de onda quadrada) e
+* contagem binária. O comando 0x30 utiliza o modo 0, que gera uma única interrupção na contagem final.
+*/
+const CHANNEL0_SQUARE_WAVE: u8 = 0x36;
+const CHANNEL0_ONE_SHOT: u8 = 0x30;
+
+// Calcula o divisor do oscilador para a frequência desejada. O valor 0 equivale a 65536.
+pub fn divisor_for(frequency_hz: u32) -> u16 {
+    let divisor = BASE_FREQUENCY_HZ / frequency_hz.max(1);
+    divisor.clamp(1, 65536) as u16
+}
+
+// Frequência real obtida com o divisor, já que a divisão raramente é exata.
+pub fn frequency_for(divisor: u16) -> u32 {
+    let divisor = if divisor == 0 { 65536 } else { u32::from(divisor) };
+    BASE_FREQUENCY_HZ / divisor
+}
+
+fn program_channel0(mode: u8, count: u16) {
+    let mut command: Port<u8> = Port::new(COMMAND_PORT);
+    let mut channel0: Port<u8> = Port::new(CHANNEL0_PORT);
+
+    unsafe {
+        command.write(mode);
+        channel0.write(count as u8);
+        channel0.write((count >> 8) as u8);
+    }
+}
+
+// Programa o canal 0 para gerar interrupções periódicas com o divisor informado.
+pub fn set_periodic(divisor: u16) {
+    program_channel0(CHANNEL0_SQUARE_WAVE, divisor);
+}
+
+// O canal 0 do PIT como fonte do tick do sistema.
+pub struct Pit;
+
+impl Timer for Pit {
+    fn name(&self) -> &'static str {
+        "PIT"
+    }
+
+    fn start_periodic(&mut self, frequency_hz: u32) -> Option<Duration> {
+        let divisor = divisor_for(frequency_hz);
+        let count = if divisor == 0 { 65536 } else { u64::from(divisor) };
+        set_periodic(divisor);
+        Some(Duration::from_nanos(count * 1_000_000_000 / u64::from(BASE_FREQUENCY_HZ)))
+    }
+
+    // O contador tem 16 bits, então o maior intervalo possível é de aproximadamente 55 ms.
+    fn start_one_shot(&mut self, delay: Duration) -> bool {
+        let count = delay.as_nanos() * u128::from(BASE_FREQUENCY_HZ) / 1_000_000_000;
+        if count == 0 || count > 65535 {
+            return false;
+        }
+        program_channel0(CHANNEL0_ONE_SHOT, count as u16);
+        true
+    }
+
+    fn stop(&mut self) {
+        program_channel0(CHANNEL0_ONE_SHOT, 0xffff);                                                 // Uma única contagem final e nenhuma recarga
+    }
+}
+
+const CHANNEL2_PORT: u16 = 0x42;
+const SPEAKER_PORT: u16 = 0x61;
+const SPEAKER_GATE: u8 = 1 << 0;                                                                    // Habilita a contagem do canal 2
+const SPEAKER_ENABLE: u8 = 1 << 1;
+const CHANNEL2_OUTPUT: u8 = 1 << 5;
+
+// Comando 0xb0: canal 2, byte baixo e depois alto, modo 0 (interrupção na contagem final), binário.
+const CHANNEL2_ONE_SHOT: u8 = 0xb0;
+
+/* Espera o canal 2 contar o número de pulsos informado, consultando a saída através da porta 0x61.
+* O canal 2 normalmente alimenta o alto-falante, que fica desligado durante a espera. Como não
+* depende de interrupções, pode ser utilizado para calibrar outros relógios durante a inicialização.
+*/
+pub fn wait_channel2(count: u16) {
+    let mut command: Port<u8> = Port::new(COMMAND_PORT);
+    let mut channel2: Port<u8> = Port::new(CHANNEL2_PORT);
+    let mut speaker: Port<u8> = Port::new(SPEAKER_PORT);
+
+    unsafe {
+        let control = speaker.read() & !(SPEAKER_ENABLE | SPEAKER_GATE);
+        speaker.write(control);                                                                     // Desliga o gate para reiniciar a contagem
+
+        command.write(CHANNEL2_ONE_SHOT);
+        channel2.write(count as u8);
+        channel2.write((count >> 8) as u8);
+
+        speaker.write(control | SPEAKER_GATE);
+        while speaker.read() & CHANNEL2_OUTPUT == 0 {
+            core::hint::spin_loop();
+        }
+        speaker.write(control);
+    }
+}
@@ -0,0 +1,86 @@
+use x86_64::instructions::port::Port;
+
+/* O controlador PS/2 (8042) possui duas portas: a primeira ligada ao teclado (IRQ 1) e a auxiliar
+* ligada ao mouse (IRQ 12). Os dados dos dois dispositivos chegam pela porta 0x60 e a porta 0x64 é
+* utilizada para ler o estado do controlador e enviar comandos a ele.
+*/
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+const COMMAND_PORT: u16 = 0x64;
+
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+
+pub const COMMAND_READ_CONFIG: u8 = 0x20;
+pub const COMMAND_WRITE_CONFIG: u8 = 0x60;
+pub const COMMAND_ENABLE_AUX: u8 = 0xa8;
+pub const COMMAND_WRITE_AUX: u8 = 0xd4;
+pub const COMMAND_PULSE_RESET: u8 = 0xfe;                                                           // Pulsa a linha de reset da CPU
+
+pub const CONFIG_KEYBOARD_IRQ: u8 = 1 << 0;
+pub const CONFIG_AUX_IRQ: u8 = 1 << 1;
+pub const CONFIG_AUX_CLOCK_DISABLED: u8 = 1 << 5;
+pub const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+pub const DEVICE_ACK: u8 = 0xfa;
+pub const DEVICE_RESEND: u8 = 0xfe;
+
+const TIMEOUT: u32 = 100_000;                                                                       // Tentativas antes de desistir de um dispositivo que não responde
+
+fn status() -> u8 {
+    unsafe { Port::new(STATUS_PORT).read() }
+}
+
+// Espera o controlador consumir o último byte escrito antes de enviar outro.
+pub fn wait_input_empty() -> bool {
+    (0..TIMEOUT).any(|_| status() & STATUS_INPUT_FULL == 0)
+}
+
+pub fn write_command(command: u8) {
+    wait_input_empty();
+    unsafe { Port::new(COMMAND_PORT).write(command) };
+}
+
+pub fn write_data(byte: u8) {
+    wait_input_empty();
+    unsafe { Port::new(DATA_PORT).write(byte) };
+}
+
+// Lê a porta de dados sem verificar o estado, utilizado pelos manipuladores de interrupção.
+pub fn read_data() -> u8 {
+    unsafe { Port::new(DATA_PORT).read() }
+}
+
+// Espera um byte do controlador ou de um dispositivo, retornando None se ele não chegar.
+pub fn poll_data() -> Option<u8> {
+    if (0..TIMEOUT).any(|_| status() & STATUS_OUTPUT_FULL != 0) {
+        Some(read_data())
+    } else {
+        None
+    }
+}
+
+// Descarta os bytes pendentes na porta de dados.
+pub fn flush() {
+    while status() & STATUS_OUTPUT_FULL != 0 {
+        read_data();
+    }
+}
+
+pub fn read_config() -> Option<u8> {
+    write_command(COMMAND_READ_CONFIG);
+    poll_data()
+}
+
+pub fn write_config(config: u8) {
+    write_command(COMMAND_WRITE_CONFIG);
+    write_data(config);
+}
+
+/* Envia um byte ao mouse: o comando 0xd4 faz o controlador encaminhar o próximo byte da porta de
+* dados para a porta auxiliar em vez do teclado.
+*/
+pub fn write_aux(byte: u8) {
+    write_command(COMMAND_WRITE_AUX);
+    write_data(byte);
+}
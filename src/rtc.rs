@@ -0,0 +1,300 @@
+use crate::hpet;
+use crate::interrupts::{self, InterruptIndex, IrqError};
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+use spin::{Mutex, Once};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+
+/* O RTC (Real Time Clock) fica na memória CMOS, alimentada por uma bateria, e mantém a data e a hora
+* mesmo com a máquina desligada. Os registradores são acessados escrevendo o índice na porta 0x70 e
+* lendo ou escrevendo o valor na porta 0x71.
+*/
+const CMOS_ADDRESS_PORT: u16 = 0x70;
+const CMOS_DATA_PORT: u16 = 0x71;
+const NMI_DISABLE: u8 = 1 << 7;                                                                     // O bit 7 da porta de índice desabilita as NMIs
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0a;
+const REG_STATUS_B: u8 = 0x0b;
+const REG_STATUS_C: u8 = 0x0c;
+const REG_STATUS_D: u8 = 0x0d;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const STATUS_B_PERIODIC_INTERRUPT: u8 = 1 << 6;
+const HOUR_PM: u8 = 1 << 7;
+
+/* As NMIs ficam desabilitadas apenas durante o acesso. Em muitos chipsets a porta 0x70 não pode ser
+* lida, então o estado escolhido com set_nmi_enabled é guardado aqui e restaurado ao final de cada
+* acesso, junto com o índice do registrador D, que é somente leitura.
+*/
+struct Cmos {
+    address: Port<u8>,
+    data: Port<u8>,
+    nmi_disabled: bool,
+}
+
+// Valor escrito na porta de índice para selecionar um registrador com as NMIs no estado informado.
+fn index_byte(register: u8, nmi_disabled: bool) -> u8 {
+    if nmi_disabled { NMI_DISABLE | register } else { register }
+}
+
+impl Cmos {
+    fn read(&mut self, register: u8) -> u8 {
+        unsafe {
+            self.address.write(index_byte(register, true));
+            let value = self.data.read();
+            self.restore_nmi();
+            value
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        unsafe {
+            self.address.write(index_byte(register, true));
+            self.data.write(value);
+            self.restore_nmi();
+        }
+    }
+
+    fn restore_nmi(&mut self) {
+        unsafe { self.address.write(index_byte(REG_STATUS_D, self.nmi_disabled)) };
+    }
+}
+
+/* O índice e o valor são escritos em portas separadas, então o acesso precisa ser exclusivo e sem
+* interrupções entre as duas operações.
+*/
+lazy_static! {
+    static ref CMOS: Mutex<Cmos> = Mutex::new(Cmos {
+        address: Port::new(CMOS_ADDRESS_PORT),
+        data: Port::new(CMOS_DATA_PORT),
+        nmi_disabled: false,
+    });
+}
+
+// Habilita ou desabilita as NMIs através do bit 7 da porta de índice do CMOS.
+pub fn set_nmi_enabled(enabled: bool) {
+    without_interrupts(|| {
+        let mut cmos = CMOS.lock();
+        cmos.nmi_disabled = !enabled;
+        cmos.restore_nmi();
+    });
+}
+
+pub fn nmi_enabled() -> bool {
+    without_interrupts(|| !CMOS.lock().nmi_disabled)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+               self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+// Valores crus lidos dos registradores, ainda no formato configurado no registrador B.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RawDateTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0f) + (value >> 4) * 10
+}
+
+/* O registrador de século não é padronizado: a FADT do ACPI informa o seu índice (normalmente 0x32),
+* ou 0 quando ele não existe. A tabela é consultada uma única vez.
+*/
+static CENTURY_REGISTER: Once<Option<u8>> = Once::new();
+
+fn century_register() -> Option<u8> {
+    *CENTURY_REGISTER.call_once(crate::acpi::century_register)
+}
+
+fn read_raw(cmos: &mut Cmos, century_register: Option<u8>) -> RawDateTime {
+    /* Durante a atualização do relógio (cerca de 2 ms por segundo) os registradores podem conter
+    * valores inconsistentes, então esperamos o fim da atualização antes da leitura.
+    */
+    while cmos.read(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+
+    RawDateTime {
+        second: cmos.read(REG_SECONDS),
+        minute: cmos.read(REG_MINUTES),
+        hour: cmos.read(REG_HOURS),
+        day: cmos.read(REG_DAY),
+        month: cmos.read(REG_MONTH),
+        year: cmos.read(REG_YEAR),
+        century: century_register.map_or(0, |register| cmos.read(register)),
+    }
+}
+
+// Converte os valores crus conforme o registrador B, que indica se estão em BCD e se a hora é 12h.
+fn decode(raw: RawDateTime, status_b: u8) -> DateTime {
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let convert = |value: u8| if binary { value } else { bcd_to_binary(value) };
+
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = convert(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24_HOUR == 0 {
+        hour %= 12;                                                                                 // 12 AM é meia-noite e 12 PM é meio-dia
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let century = match convert(raw.century) {
+        0 => 20,                                                                                    // Registrador de século ausente
+        century => century,
+    };
+
+    DateTime {
+        year: u16::from(century) * 100 + u16::from(convert(raw.year)),
+        month: convert(raw.month),
+        day: convert(raw.day),
+        hour,
+        minute: convert(raw.minute),
+        second: convert(raw.second),
+    }
+}
+
+/* Lê a data e a hora atuais. A leitura é repetida até duas leituras consecutivas serem iguais, o que
+* garante que nenhuma atualização ocorreu no meio dela.
+*/
+pub fn now() -> DateTime {
+    let century_register = century_register();
+    without_interrupts(|| {
+        let mut cmos = CMOS.lock();
+        let mut raw = read_raw(&mut cmos, century_register);
+        loop {
+            let again = read_raw(&mut cmos, century_register);
+            if again == raw {
+                break;
+            }
+            raw = again;
+        }
+        let status_b = cmos.read(REG_STATUS_B);
+        decode(raw, status_b)
+    })
+}
+
+static PERIODIC_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/* Habilita a interrupção periódica do RTC na IRQ 8. A frequência é 32768 >> (rate - 1) Hz, com rate
+* entre 3 (8192 Hz) e 15 (2 Hz). Falha quando o HPET está no modo de substituição legado, que liga o
+* timer 1 do HPET na IRQ 8 no lugar do RTC.
+*/
+pub fn enable_periodic_interrupt(rate: u8) -> Result<(), IrqError> {
+    let rate = rate.clamp(3, 15);
+    if hpet::legacy_replacement_active() {
+        return Err(IrqError::Rerouted(InterruptIndex::RealTimeClock.irq()));
+    }
+    interrupts::register_irq(InterruptIndex::RealTimeClock.irq(), periodic_interrupt)?;
+
+    without_interrupts(|| {
+        let mut cmos = CMOS.lock();
+        let status_a = cmos.read(REG_STATUS_A);
+        cmos.write(REG_STATUS_A, (status_a & 0xf0) | rate);
+        let status_b = cmos.read(REG_STATUS_B);
+        cmos.write(REG_STATUS_B, status_b | STATUS_B_PERIODIC_INTERRUPT);
+        cmos.read(REG_STATUS_C);                                                                    // Descarta uma interrupção pendente
+    });
+    Ok(())
+}
+
+pub fn disable_periodic_interrupt() {
+    without_interrupts(|| {
+        let mut cmos = CMOS.lock();
+        let status_b = cmos.read(REG_STATUS_B);
+        cmos.write(REG_STATUS_B, status_b & !STATUS_B_PERIODIC_INTERRUPT);
+        cmos.read(REG_STATUS_C);
+    });
+    interrupts::unregister_irq(InterruptIndex::RealTimeClock.irq());
+}
+
+pub fn periodic_frequency(rate: u8) -> u32 {
+    32768 >> (rate.clamp(3, 15) - 1)
+}
+
+pub fn periodic_ticks() -> u64 {
+    PERIODIC_TICKS.load(Ordering::Relaxed)
+}
+
+// O registrador C precisa ser lido a cada interrupção, senão o RTC não gera a próxima.
+fn periodic_interrupt() {
+    CMOS.lock().read(REG_STATUS_C);
+    PERIODIC_TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test_case]
+fn test_decode_bcd_12_hour() {
+    let raw = RawDateTime { second: 0x59, minute: 0x30, hour: HOUR_PM | 0x12, day: 0x31, month: 0x12, year: 0x24, century: 0x20 };
+    let date_time = decode(raw, 0);
+    assert_eq!(date_time, DateTime { year: 2024, month: 12, day: 31, hour: 12, minute: 30, second: 59 });
+
+    let raw = RawDateTime { hour: 0x12, ..raw };
+    assert_eq!(decode(raw, 0).hour, 0);
+}
+
+#[test_case]
+fn test_decode_binary_24_hour() {
+    let raw = RawDateTime { second: 5, minute: 4, hour: 23, day: 2, month: 1, year: 25, century: 20 };
+    let date_time = decode(raw, STATUS_B_BINARY | STATUS_B_24_HOUR);
+    assert_eq!(date_time, DateTime { year: 2025, month: 1, day: 2, hour: 23, minute: 4, second: 5 });
+}
+
+#[test_case]
+fn test_now_is_plausible() {
+    let date_time = now();
+    assert!(date_time.year >= 2000);
+    assert!((1..=12).contains(&date_time.month));
+    assert!((1..=31).contains(&date_time.day));
+    assert!(date_time.hour < 24 && date_time.minute < 60 && date_time.second < 60);
+}
+
+#[test_case]
+fn test_index_byte() {
+    assert_eq!(index_byte(REG_STATUS_B, true), 0x8b);                                               // As NMIs ficam desabilitadas durante o acesso
+    assert_eq!(index_byte(REG_STATUS_D, false), 0x0d);
+    assert_eq!(index_byte(REG_STATUS_D, true), 0x8d);
+}
+
+#[test_case]
+fn test_periodic_interrupt() {
+    if hpet::legacy_replacement_active() {
+        assert_eq!(enable_periodic_interrupt(6), Err(IrqError::Rerouted(8)));
+        return;
+    }
+
+    let start = periodic_ticks();
+    enable_periodic_interrupt(6).unwrap();                                                          // 1024 Hz
+    crate::time::sleep(20);
+    disable_periodic_interrupt();
+    assert!(periodic_ticks() > start);
+}
@@ -1,6 +1,8 @@
+use crate::interrupts::{self, InterruptIndex, IrqError};
 use uart_16550::SerialPort;
 use spin::Mutex;
 use lazy_static::lazy_static;
+use x86_64::instructions::port::Port;
 
 /* Utilizamos a interface UART para a comunicação entre o guest e host. Podemos comunicar o qemu com
 * uma saída que pode ser uma saída padrão ou arquivo.
@@ -18,10 +20,85 @@ lazy_static! {
     };
 }
 
+/* A entrada pela serial chega na IRQ 4 (COM1) quando a interrupção de dado recebido está habilitada
+* no registrador IER. Além disso, nos PCs a saída OUT2 do registrador MCR precisa estar ligada para
+* que o UART consiga sinalizar a IRQ.
+*/
+const COM1: u16 = 0x3f8;
+const REG_INTERRUPT_ENABLE: u16 = COM1 + 1;
+const REG_MODEM_CONTROL: u16 = COM1 + 4;
+const REG_LINE_STATUS: u16 = COM1 + 5;
+const INTERRUPT_DATA_AVAILABLE: u8 = 1 << 0;
+const MODEM_CONTROL_OUT2: u8 = 1 << 3;
+const LINE_STATUS_DATA_READY: u8 = 1 << 0;
+
+const INPUT_SIZE: usize = 256;
+
+struct InputQueue {
+    bytes: [u8; INPUT_SIZE],
+    head: usize,
+    len: usize,
+}
+
+static INPUT: Mutex<InputQueue> = Mutex::new(InputQueue { bytes: [0; INPUT_SIZE], head: 0, len: 0 });
+
+pub fn init() -> Result<(), IrqError> {
+    lazy_static::initialize(&SERIAL1);
+    interrupts::register_irq(InterruptIndex::Com1.irq(), serial_interrupt)?;
+    unsafe {
+        let mut modem_control: Port<u8> = Port::new(REG_MODEM_CONTROL);
+        let value = modem_control.read();
+        modem_control.write(value | MODEM_CONTROL_OUT2);
+        Port::new(REG_INTERRUPT_ENABLE).write(INTERRUPT_DATA_AVAILABLE);
+    }
+    Ok(())
+}
+
+// Adiciona um byte na fila de entrada, como se tivesse sido recebido pela serial.
+pub fn push_input(byte: u8) {
+    x86_64::instructions::interrupts::without_interrupts(|| push(&mut INPUT.lock(), byte));
+}
+
+// Quando a fila está cheia o byte mais novo é descartado.
+fn push(input: &mut InputQueue, byte: u8) {
+    if input.len < INPUT_SIZE {
+        input.bytes[(input.head + input.len) % INPUT_SIZE] = byte;
+        input.len += 1;
+    }
+}
+
+pub fn read_byte() -> Option<u8> {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut input = INPUT.lock();
+        if input.len == 0 {
+            return None;
+        }
+        let byte = input.bytes[input.head];
+        input.head = (input.head + 1) % INPUT_SIZE;
+        input.len -= 1;
+        Some(byte)
+    })
+}
+
+// Lê todos os bytes disponíveis no UART, que pode acumular mais de um byte na FIFO.
+fn serial_interrupt() {
+    let mut line_status: Port<u8> = Port::new(REG_LINE_STATUS);
+    let mut data: Port<u8> = Port::new(COM1);
+    let mut input = INPUT.lock();
+    while unsafe { line_status.read() } & LINE_STATUS_DATA_READY != 0 {
+        let byte = unsafe { data.read() };
+        push(&mut input, byte);
+    }
+}
+
 #[doc(hidden)]
 pub fn _print(args: ::core::fmt::Arguments) {
     use core::fmt::Write;
-    SERIAL1.lock().write_fmt(args).unwrap();
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {                                                             // Mesmo motivo do _print do vga_buffer: evita deadlock com manipuladores de interrupção.
+        SERIAL1.lock().write_fmt(args).unwrap();
+    });
 }
 
 #[macro_export]
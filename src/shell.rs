@@ -0,0 +1,478 @@
+use crate::interrupts::{self, IRQ_COUNT};
+use crate::vga_buffer;
+use crate::{clock, console, console_print, console_println, memory, ps2, time};
+use spin::Mutex;
+
+/* Shell interativo do kernel. Cada comando implementa o trait Command e fica em um registro de
+* tamanho fixo, que começa com os comandos embutidos e aceita novos comandos através de register.
+* As linhas são lidas pelo console, então o shell pode ser utilizado tanto pelo teclado quanto pela
+* serial, e a resposta é escrita nos dois.
+*/
+pub trait Command: Sync {
+    fn name(&self) -> &'static str;
+    fn help(&self) -> &'static str;
+
+    // Recebe os argumentos sem o nome do comando e retorna uma mensagem em caso de erro.
+    fn run(&self, args: &[&str]) -> Result<(), &'static str>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellError {
+    UnknownCommand,
+    Tokenize(TokenizeError),
+    Command(&'static str),
+    RegistryFull,
+    AlreadyRegistered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizeError {
+    UnterminatedQuote,
+    TooManyArguments,
+    LineTooLong,
+}
+
+pub const MAX_ARGS: usize = 16;
+const TOKEN_BUFFER_SIZE: usize = console::MAX_LINE * 4;
+
+/* Argumentos de uma linha já separados. Como as aspas e as barras invertidas são removidas, o texto
+* dos argumentos é copiado para um buffer próprio.
+*/
+pub struct Tokens {
+    buffer: [u8; TOKEN_BUFFER_SIZE],
+    used: usize,
+    bounds: [(usize, usize); MAX_ARGS],
+    count: usize,
+    start: usize,                                                                                   // Início do argumento em construção
+}
+
+impl Tokens {
+    const fn new() -> Tokens {
+        Tokens { buffer: [0; TOKEN_BUFFER_SIZE], used: 0, bounds: [(0, 0); MAX_ARGS], count: 0, start: 0 }
+    }
+
+    fn push_char(&mut self, character: char) -> Result<(), TokenizeError> {
+        if self.used + character.len_utf8() > TOKEN_BUFFER_SIZE {
+            return Err(TokenizeError::LineTooLong);
+        }
+        self.used += character.encode_utf8(&mut self.buffer[self.used..]).len();
+        Ok(())
+    }
+
+    fn finish_token(&mut self) -> Result<(), TokenizeError> {
+        if self.count == MAX_ARGS {
+            return Err(TokenizeError::TooManyArguments);
+        }
+        self.bounds[self.count] = (self.start, self.used);
+        self.count += 1;
+        self.start = self.used;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        let (start, end) = *self.bounds[..self.count].get(index)?;
+        core::str::from_utf8(&self.buffer[start..end]).ok()
+    }
+}
+
+/* Separa a linha em argumentos pelos espaços. Aspas simples preservam o texto literalmente, aspas
+* duplas permitem escapar caracteres com "\" e fora das aspas "\" escapa o próximo caractere.
+*/
+pub fn tokenize(line: &str) -> Result<Tokens, TokenizeError> {
+    let mut tokens = Tokens::new();
+    let mut quote: Option<char> = None;
+    let mut escape = false;
+    let mut in_token = false;
+
+    for character in line.chars() {
+        if escape {
+            tokens.push_char(character)?;
+            escape = false;
+            continue;
+        }
+        match (quote, character) {
+            (None, '\\') | (Some('"'), '\\') => {
+                escape = true;
+                in_token = true;
+            }
+            (None, '"') | (None, '\'') => {
+                quote = Some(character);
+                in_token = true;                                                                    // "" gera um argumento vazio
+            }
+            (Some(open), character) if character == open => quote = None,
+            (None, character) if character.is_whitespace() => {
+                if in_token {
+                    tokens.finish_token()?;
+                    in_token = false;
+                }
+            }
+            (_, character) => {
+                tokens.push_char(character)?;
+                in_token = true;
+            }
+        }
+    }
+
+    if quote.is_some() || escape {
+        return Err(TokenizeError::UnterminatedQuote);
+    }
+    if in_token {
+        tokens.finish_token()?;
+    }
+    Ok(tokens)
+}
+
+struct Help;
+struct Clear;
+struct Echo;
+struct Uptime;
+struct Mem;
+struct Irqs;
+struct Reboot;
+struct Halt;
+struct Console;
+
+impl Command for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn help(&self) -> &'static str {
+        "lista os comandos ou mostra a ajuda de um comando"
+    }
+
+    fn run(&self, args: &[&str]) -> Result<(), &'static str> {
+        match args {
+            [] => {
+                commands().iter().flatten().for_each(|command| {
+                    console_println!("  {:<10} {}", command.name(), command.help());
+                });
+                Ok(())
+            }
+            [name] => {
+                let command = find(name).ok_or("comando desconhecido")?;
+                console_println!("{}: {}", command.name(), command.help());
+                Ok(())
+            }
+            _ => Err("uso: help [comando]"),
+        }
+    }
+}
+
+impl Command for Clear {
+    fn name(&self) -> &'static str {
+        "clear"
+    }
+
+    fn help(&self) -> &'static str {
+        "limpa a tela"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        vga_buffer::clear_screen();
+        crate::serial_print!("\x1b[2J\x1b[H");                                                      // Limpa também o terminal da serial
+        Ok(())
+    }
+}
+
+impl Command for Echo {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn help(&self) -> &'static str {
+        "imprime os argumentos"
+    }
+
+    fn run(&self, args: &[&str]) -> Result<(), &'static str> {
+        for (index, arg) in args.iter().enumerate() {
+            if index > 0 {
+                console_print!(" ");
+            }
+            console_print!("{}", arg);
+        }
+        console_println!();
+        Ok(())
+    }
+}
+
+impl Command for Uptime {
+    fn name(&self) -> &'static str {
+        "uptime"
+    }
+
+    fn help(&self) -> &'static str {
+        "mostra o tempo desde a inicializacao"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        let uptime = time::uptime();
+        let seconds = uptime.as_secs();
+        console_println!("ativo ha {}:{:02}:{:02}.{:03} ({} ticks, {} a {} Hz, relogio {})",
+                         seconds / 3600, seconds / 60 % 60, seconds % 60, uptime.subsec_millis(),
+                         time::ticks(), time::tick_source(), time::frequency(), clock::source().name());
+        Ok(())
+    }
+}
+
+impl Command for Mem {
+    fn name(&self) -> &'static str {
+        "mem"
+    }
+
+    fn help(&self) -> &'static str {
+        "mostra o mapa de memoria fisica"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        let map = memory::memory_map().ok_or("mapa de memoria indisponivel")?;
+        for region in map.iter() {
+            console_println!("  {:#012x}-{:#012x} {:?}",
+                             region.range.start_addr(), region.range.end_addr(), region.region_type);
+        }
+        console_println!("memoria livre: {} KiB", memory::usable_memory() / 1024);
+        Ok(())
+    }
+}
+
+const IRQ_NAMES: [&str; IRQ_COUNT] = [
+    "timer", "teclado", "cascata", "COM2", "COM1", "LPT2", "disquete", "LPT1",
+    "RTC", "ACPI", "livre", "livre", "mouse", "coprocessador", "ATA primario", "ATA secundario",
+];
+
+impl Command for Irqs {
+    fn name(&self) -> &'static str {
+        "irqs"
+    }
+
+    fn help(&self) -> &'static str {
+        "mostra as interrupcoes recebidas em cada linha"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        console_println!("controlador: {:?}", interrupts::controller());
+        for (irq, name) in (0..).zip(IRQ_NAMES) {
+            let count = interrupts::irq_count(irq);
+            let registered = interrupts::is_irq_registered(irq);
+            if registered || count > 0 {
+                console_println!("  IRQ {:>2} {:<14} {:>10}{}", irq, name, count, if registered { "" } else { " (sem manipulador)" });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Command for Reboot {
+    fn name(&self) -> &'static str {
+        "reboot"
+    }
+
+    fn help(&self) -> &'static str {
+        "reinicia a maquina"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        console_println!("Reiniciando...");
+        reboot()
+    }
+}
+
+impl Command for Halt {
+    fn name(&self) -> &'static str {
+        "halt"
+    }
+
+    fn help(&self) -> &'static str {
+        "para a CPU"
+    }
+
+    fn run(&self, _args: &[&str]) -> Result<(), &'static str> {
+        console_println!("Sistema parado.");
+        x86_64::instructions::interrupts::disable();
+        loop {
+            x86_64::instructions::hlt();
+        }
+    }
+}
+
+impl Command for Console {
+    fn name(&self) -> &'static str {
+        "console"
+    }
+
+    fn help(&self) -> &'static str {
+        "mostra ou troca o console virtual exibido (1 a 6, ou Alt+F1 a Alt+F6)"
+    }
+
+    // Os consoles são numerados a partir de 1, como as teclas de função que os exibem.
+    fn run(&self, args: &[&str]) -> Result<(), &'static str> {
+        match args {
+            [] => {
+                console_println!("console ativo: {} de {}", vga_buffer::active_console() + 1, vga_buffer::CONSOLE_COUNT);
+                Ok(())
+            }
+            [number] => {
+                let index = number.parse::<usize>().ok().and_then(|number| number.checked_sub(1)).ok_or("numero de console invalido")?;
+                if vga_buffer::switch_console(index) {
+                    Ok(())
+                } else {
+                    Err("console inexistente")
+                }
+            }
+            _ => Err("uso: console [numero]"),
+        }
+    }
+}
+
+/* Pede ao controlador PS/2 um pulso na linha de reset da CPU. Se a máquina não reiniciar, carrega uma
+* IDT vazia e gera uma exceção, o que causa um triple fault e reinicia a CPU.
+*/
+pub fn reboot() -> ! {
+    use x86_64::instructions::tables::lidt;
+    use x86_64::structures::DescriptorTablePointer;
+
+    x86_64::instructions::interrupts::disable();
+    ps2::write_command(ps2::COMMAND_PULSE_RESET);
+    (0..1_000_000).for_each(|_| core::hint::spin_loop());                                           // As interrupções estão desabilitadas, então o sleep não pode ser usado
+
+    let empty = DescriptorTablePointer { limit: 0, base: x86_64::VirtAddr::new(0) };
+    unsafe {
+        lidt(&empty);
+        core::arch::asm!("int3", options(nomem, nostack));
+    }
+    crate::hlt_loop()
+}
+
+const MAX_COMMANDS: usize = 32;
+
+type Registry = [Option<&'static dyn Command>; MAX_COMMANDS];
+
+const fn builtins() -> Registry {
+    let mut registry: Registry = [None; MAX_COMMANDS];
+    registry[0] = Some(&Help);
+    registry[1] = Some(&Clear);
+    registry[2] = Some(&Echo);
+    registry[3] = Some(&Uptime);
+    registry[4] = Some(&Mem);
+    registry[5] = Some(&Irqs);
+    registry[6] = Some(&Reboot);
+    registry[7] = Some(&Halt);
+    registry[8] = Some(&Console);
+    registry
+}
+
+static COMMANDS: Mutex<Registry> = Mutex::new(builtins());
+
+fn commands() -> Registry {
+    *COMMANDS.lock()
+}
+
+pub fn find(name: &str) -> Option<&'static dyn Command> {
+    commands().iter().flatten().copied().find(|command| command.name() == name)
+}
+
+pub fn register(command: &'static dyn Command) -> Result<(), ShellError> {
+    let mut registry = COMMANDS.lock();
+    if registry.iter().flatten().any(|registered| registered.name() == command.name()) {
+        return Err(ShellError::AlreadyRegistered);
+    }
+    let slot = registry.iter_mut().find(|slot| slot.is_none()).ok_or(ShellError::RegistryFull)?;
+    *slot = Some(command);
+    Ok(())
+}
+
+// Executa uma linha de comando. Linhas vazias são ignoradas.
+pub fn execute(line: &str) -> Result<(), ShellError> {
+    let tokens = tokenize(line).map_err(ShellError::Tokenize)?;
+    let name = match tokens.get(0) {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    let command = find(name).ok_or(ShellError::UnknownCommand)?;
+
+    let mut args = [""; MAX_ARGS];
+    for (index, arg) in args.iter_mut().enumerate().take(tokens.len()) {
+        *arg = tokens.get(index).unwrap_or("");
+    }
+    command.run(&args[1..tokens.len()]).map_err(ShellError::Command)
+}
+
+const PROMPT: &str = "> ";
+
+// Lê e executa uma linha, exibindo o erro caso o comando falhe.
+pub fn run_once() -> Result<(), ShellError> {
+    let line = console::read_line(PROMPT);
+    let result = execute(&line);
+    match result {
+        Err(ShellError::UnknownCommand) => console_println!("comando desconhecido, digite help"),
+        Err(ShellError::Tokenize(error)) => console_println!("linha invalida: {:?}", error),
+        Err(ShellError::Command(message)) => console_println!("erro: {}", message),
+        _ => {}
+    }
+    result
+}
+
+pub fn run() -> ! {
+    console_println!("Shell do kernel, digite help para ver os comandos.");
+    loop {
+        let _ = run_once();
+    }
+}
+
+#[test_case]
+fn test_tokenize_quotes() {
+    let tokens = tokenize(r#"echo  "a b" 'c\d' e\ f "" "g\"h""#).unwrap();
+    let expected = ["echo", "a b", "c\\d", "e f", "", "g\"h"];
+    assert_eq!(tokens.len(), expected.len());
+    for (index, arg) in expected.iter().enumerate() {
+        assert_eq!(tokens.get(index), Some(*arg));
+    }
+
+    assert_eq!(tokenize("echo \"abc").err(), Some(TokenizeError::UnterminatedQuote));
+    assert!(tokenize("   ").unwrap().is_empty());
+}
+
+#[test_case]
+fn test_register_and_execute() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static ARGS: AtomicUsize = AtomicUsize::new(0);
+    struct Count;
+    impl Command for Count {
+        fn name(&self) -> &'static str {
+            "count"
+        }
+
+        fn help(&self) -> &'static str {
+            "conta os argumentos"
+        }
+
+        fn run(&self, args: &[&str]) -> Result<(), &'static str> {
+            ARGS.store(args.len(), Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    register(&Count).unwrap();
+    assert_eq!(register(&Count), Err(ShellError::AlreadyRegistered));
+    execute("count 'um arg' dois").unwrap();
+    assert_eq!(ARGS.load(Ordering::SeqCst), 2);
+    assert_eq!(execute("inexistente"), Err(ShellError::UnknownCommand));
+    assert_eq!(execute("help inexistente"), Err(ShellError::Command("comando desconhecido")));
+}
+
+#[test_case]
+fn test_run_once_from_serial() {
+    for &byte in b"echo \"pela serial\"\r" {
+        crate::serial::push_input(byte);
+    }
+    assert_eq!(run_once(), Ok(()));
+}
@@ -0,0 +1,160 @@
+use crate::vga_buffer::{self, Color, Pane, Region, BUFFER_HEIGHT, CONSOLE_COUNT, CONSOLES};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use spin::Mutex;
+
+/* Linha de status reservada no topo ou no final da tela de todos os consoles, com o tempo desde a
+* inicialização, a memória livre e o console ativo. A região do Writer de cada console perde essa
+* linha, então o texto rola apenas nas linhas restantes. A linha é redesenhada fora das interrupções,
+* quando o shell está ocioso esperando uma tecla, se o segundo ou o console ativo mudaram.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Top,
+    Bottom,
+}
+
+struct StatusBar {
+    panes: [Pane; CONSOLE_COUNT],
+    free_memory: u64,                                                                               // Calculada uma vez, pois ainda não há alocador de frames
+}
+
+static STATUS_BAR: Mutex<Option<StatusBar>> = Mutex::new(None);
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LAST_SECOND: AtomicU64 = AtomicU64::new(u64::MAX);
+static LAST_CONSOLE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+// Habilitada pela opção "statusbar" da linha de comando, no final da tela ou no topo com "statusbar=top".
+pub fn init() {
+    match crate::cmdline::get("statusbar") {
+        Some("top") => enable(Position::Top),
+        Some(_) => enable(Position::Bottom),
+        None if crate::cmdline::has_flag("statusbar") => enable(Position::Bottom),
+        None => {}
+    }
+}
+
+pub fn enable(position: Position) {
+    use x86_64::instructions::interrupts;
+
+    let (status, text) = match position {
+        Position::Top => (Region::new(0, 1), Region::new(1, BUFFER_HEIGHT - 1)),
+        Position::Bottom => (Region::new(BUFFER_HEIGHT - 1, 1), Region::new(0, BUFFER_HEIGHT - 1)),
+    };
+    let free_memory = crate::memory::usable_memory();
+    interrupts::without_interrupts(|| {
+        for console in CONSOLES.iter() {
+            console.lock().set_region(text);
+        }
+        let panes = core::array::from_fn(|console| {
+            let mut pane = Pane::new(console, status).expect("regiao da linha de status invalida");
+            pane.set_color(Color::White, Color::Blue);
+            pane
+        });
+        *STATUS_BAR.lock() = Some(StatusBar { panes, free_memory });
+        ENABLED.store(true, Ordering::SeqCst);
+        refresh();
+    });
+}
+
+// Remove a linha de status, devolvendo a tela inteira aos consoles.
+pub fn disable() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        ENABLED.store(false, Ordering::SeqCst);
+        if let Some(mut status_bar) = STATUS_BAR.lock().take() {
+            for pane in status_bar.panes.iter_mut() {
+                pane.set_color(Color::Yellow, Color::Black);
+                pane.clear();
+            }
+        }
+        for console in CONSOLES.iter() {
+            console.lock().set_region(Region::FULL_SCREEN);
+        }
+    });
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+// Chamada pelo laço ocioso do shell, redesenha a linha apenas quando o conteúdo muda.
+pub fn poll() {
+    if !is_enabled() {
+        return;
+    }
+    let second = crate::time::uptime().as_secs();
+    let console = vga_buffer::active_console();
+    let changed_second = LAST_SECOND.swap(second, Ordering::Relaxed) != second;
+    let changed_console = LAST_CONSOLE.swap(console, Ordering::Relaxed) != console;
+    if changed_second || changed_console {
+        refresh();
+    }
+}
+
+// Redesenha a linha de status em todos os consoles.
+pub fn refresh() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut status_bar = STATUS_BAR.lock();
+        let status_bar = match status_bar.as_mut() {
+            Some(status_bar) => status_bar,
+            None => return,
+        };
+        let seconds = crate::time::uptime().as_secs();
+        let free_memory = status_bar.free_memory;
+        let active = vga_buffer::active_console();
+        for pane in status_bar.panes.iter_mut() {
+            pane.set_position(0, 0);
+            let _ = write!(
+                pane,
+                " ativo {:02}:{:02}:{:02} | memoria livre {} MiB | console {} de {}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60,
+                free_memory / (1024 * 1024),
+                active + 1,
+                CONSOLE_COUNT
+            );
+            pane.clear_to_end_of_line();
+        }
+    });
+}
+
+#[test_case]
+fn test_status_bar() {
+    use x86_64::instructions::interrupts;
+
+    let region = || interrupts::without_interrupts(|| vga_buffer::WRITER.lock().region());
+    enable(Position::Top);
+    assert!(is_enabled());
+    assert_eq!(region(), Region::new(1, BUFFER_HEIGHT - 1));
+    crate::println!("texto abaixo da linha de status");
+    interrupts::without_interrupts(|| {
+        let mut writer = vga_buffer::WRITER.lock();
+        writer.set_position(0, 0);
+        assert_eq!(writer.position(), (1, 0));
+        writer.set_position(BUFFER_HEIGHT - 1, 0);
+    });
+
+    let status_text = |console: usize| interrupts::without_interrupts(|| CONSOLES[console].lock().row_bytes(0));
+    let row = status_text(0);
+    let text = core::str::from_utf8(&row).unwrap();
+    let uptime = text.strip_prefix(" ativo ").unwrap().as_bytes();
+    assert!(uptime[..8].iter().enumerate().all(|(i, &byte)| if i % 3 == 2 { byte == b':' } else { byte.is_ascii_digit() }));
+    let free_memory = text.split("memoria livre ").nth(1).unwrap().split(' ').next().unwrap();
+    assert_eq!(free_memory.parse::<u64>(), Ok(crate::memory::usable_memory() / (1024 * 1024)));
+    assert!(text.contains(" MiB | console 1 de 6 "));
+
+    assert!(vga_buffer::switch_console(1));
+    poll();
+    let row = status_text(1);
+    assert!(core::str::from_utf8(&row).unwrap().contains("| console 2 de 6 "));
+    assert!(vga_buffer::switch_console(0));
+
+    disable();
+    assert!(!is_enabled());
+    assert_eq!(region(), Region::FULL_SCREEN);
+}
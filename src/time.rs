@@ -0,0 +1,116 @@
+use crate::interrupts::{self, InterruptIndex};
+use crate::{hpet, pit};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use core::time::Duration;
+
+/* Dispositivo capaz de gerar as interrupções do tick do sistema na IRQ 0. Assim o PIT pode ser
+* substituído por outro timer (como o HPET) sem alterar o manipulador do tick.
+*/
+pub trait Timer {
+    fn name(&self) -> &'static str;
+
+    // Gera interrupções periódicas e retorna o período real obtido, ou None se não for suportado.
+    fn start_periodic(&mut self, frequency_hz: u32) -> Option<Duration>;
+
+    // Gera uma única interrupção após o intervalo informado. Retorna false se não for suportado.
+    fn start_one_shot(&mut self, delay: Duration) -> bool;
+
+    fn stop(&mut self);
+}
+
+/* Contador global de ticks do sistema. A cada interrupção do timer o contador é incrementado, então o
+* tempo desde a inicialização é o número de ticks multiplicado pelo período de cada tick.
+*/
+pub const DEFAULT_FREQUENCY_HZ: u32 = 1000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static FREQUENCY_HZ: AtomicU32 = AtomicU32::new(0);
+static TICK_PERIOD_NS: AtomicU64 = AtomicU64::new(0);
+static HPET_TICK: AtomicBool = AtomicBool::new(false);
+
+/* Programa a fonte do tick com a frequência da opção "timer_hz" da linha de comando (ou 1000 Hz por
+* padrão) e registra o manipulador da IRQ 0. A opção "timer=hpet" utiliza o HPET no lugar do PIT
+* quando ele está presente e suporta o modo periódico.
+*/
+pub fn init() {
+    let frequency = crate::cmdline::get("timer_hz")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_FREQUENCY_HZ);
+
+    let hpet_period = if crate::cmdline::get("timer") == Some("hpet") {
+        hpet::HPET.lock().as_mut().and_then(|hpet| hpet.start_periodic(frequency))
+    } else {
+        None
+    };
+    HPET_TICK.store(hpet_period.is_some(), Ordering::SeqCst);
+    let period = match hpet_period {
+        Some(period) => {
+            pit::Pit.stop();                                                                        // O canal 0 do PIT pode continuar ativo desde o BIOS
+            period
+        }
+        None => pit::Pit.start_periodic(frequency).expect("O PIT sempre suporta o modo periódico"),
+    };
+
+    let period_ns = period.as_nanos() as u64;
+    TICK_PERIOD_NS.store(period_ns, Ordering::SeqCst);
+    FREQUENCY_HZ.store((1_000_000_000 / period_ns.max(1)) as u32, Ordering::SeqCst);
+
+    interrupts::register_irq(InterruptIndex::Timer.irq(), tick).expect("IRQ do timer ja registrada");
+}
+
+// Nome do timer que gera o tick do sistema.
+pub fn tick_source() -> &'static str {
+    if HPET_TICK.load(Ordering::SeqCst) { "HPET" } else { "PIT" }
+}
+
+fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+pub fn frequency() -> u32 {
+    FREQUENCY_HZ.load(Ordering::SeqCst)
+}
+
+pub fn tick_period() -> Duration {
+    Duration::from_nanos(TICK_PERIOD_NS.load(Ordering::SeqCst))
+}
+
+// Tempo decorrido desde a inicialização do timer.
+pub fn uptime() -> Duration {
+    Duration::from_nanos(ticks() * TICK_PERIOD_NS.load(Ordering::SeqCst))
+}
+
+/* Bloqueia a execução por pelo menos ms milissegundos. Entre os ticks a CPU fica parada com a
+* instrução hlt em vez de consumir processamento em um loop, por isso as interrupções precisam estar
+* habilitadas.
+*/
+pub fn sleep(ms: u64) {
+    let period = TICK_PERIOD_NS.load(Ordering::SeqCst);
+    if period == 0 {
+        return;                                                                                     // Timer ainda não inicializado
+    }
+
+    let target = ticks() + (ms * 1_000_000).div_ceil(period);
+    while ticks() < target {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[test_case]
+fn test_sleep_waits_for_ticks() {
+    let period = TICK_PERIOD_NS.load(Ordering::SeqCst);
+    let start = ticks();
+    sleep(20);
+    assert!((ticks() - start) * period >= 20 * 1_000_000);
+}
+
+#[test_case]
+fn test_uptime_advances() {
+    let before = uptime();
+    sleep(10);
+    assert!(uptime() - before >= Duration::from_millis(10));
+}
@@ -1,8 +1,11 @@
 use core::fmt;
 //use core::fmt::Write;
 use lazy_static::lazy_static;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+use crate::font::Font;
 use spin::Mutex;
 use volatile::Volatile;
+use x86_64::instructions::port::Port;
 
 
 /*
@@ -12,19 +15,22 @@ use volatile::Volatile;
 * valor em tempo de compilação, ela inicializa a si mesma quando acessada pela primeira vez.
 */
 lazy_static! {
-    pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
-        column_position: 0,
-        color_code: ColorCode::new(Color::Yellow, Color::Black),
-        buffer: unsafe {                                                                            // O bloco unsafe é necessário, pois o compilador Rust não pode provar que os ponteiros brutos que criamos são válidos. Ao colocar o unsafe dizemos ao compilador para ignorar esses possíveis erros.
-            /*
-             * O novo writer aponta para o buffer VGA em 0xb8000
-             * "as *mut Buffer" Converte esse endereço literal para um ponteiro mutável para um tipo Buffer.
-             */
-            &mut *(0xb8000 as *mut Buffer)
-        },
-    });
+    pub static ref CONSOLES: [Mutex<Writer>; CONSOLE_COUNT] = core::array::from_fn(|index| Mutex::new(Writer::new(index)));
+    // Console do kernel, utilizado pelas macros print e println e pelo shell.
+    pub static ref WRITER: &'static Mutex<Writer> = &CONSOLES[0];
 }
 
+/* Consoles virtuais: cada console possui o seu próprio Writer e um buffer em memória com o conteúdo
+* da tela. Apenas o console ativo escreve diretamente na memória do VGA, os demais escrevem no seu
+* buffer, que é copiado para o VGA quando o console é exibido.
+*/
+pub const CONSOLE_COUNT: usize = 6;
+
+static mut CONSOLE_BUFFERS: [[[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT]; CONSOLE_COUNT] =
+    [[[BLANK; BUFFER_WIDTH]; BUFFER_HEIGHT]; CONSOLE_COUNT];
+static SCROLLBACKS: [Mutex<Scrollback>; CONSOLE_COUNT] = [const { Mutex::new(Scrollback::new()) }; CONSOLE_COUNT];
+static ACTIVE_CONSOLE: AtomicUsize = AtomicUsize::new(0);
+
 
 #[allow(dead_code)]                                                                                 // Atributo utilizado para esconder avisos de código não utilizado.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]                                                        // Habilitar semântica de cópia.
@@ -57,6 +63,11 @@ impl ColorCode {
         ColorCode((background as u8) << 4 | (foreground as u8))                                     // Cada cor necessita de 4 bits, como não temos o tipo u4, precisamos deslocar os bits para encaixarmos as cores primárias e secundárias.
     }
 
+    // Troca as cores do texto e do fundo.
+    fn inverted(self) -> ColorCode {
+        ColorCode(self.0.rotate_left(4))
+    }
+
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -66,8 +77,9 @@ struct ScreenChar {
     color_code: ColorCode,
 }
 
-const BUFFER_HEIGHT: usize = 25;
-const BUFFER_WIDTH: usize = 80;
+pub const BUFFER_HEIGHT: usize = 25;
+pub const BUFFER_WIDTH: usize = 80;
+const TAB_WIDTH: usize = 8;
 
 #[repr(transparent)]
 struct Buffer {
@@ -76,20 +88,125 @@ struct Buffer {
 
 pub struct Writer {
     column_position: usize,
+    row_position: usize,
     color_code: ColorCode,
+    default_color_code: ColorCode,                                                                  // Cor restaurada pelas sequências ANSI de reset
+    bold: bool,
+    saved_position: (usize, usize),
+    scroll_top: usize,                                                                              // Região de rolagem, com as linhas inicial e final inclusivas
+    scroll_bottom: usize,
+    escape: EscapeParser,
+    region: Region,                                                                                 // Linhas da tela em que o Writer escreve
+    view_offset: usize,                                                                             // Linhas do histórico exibidas acima da tela atual, 0 na visão ao vivo
+    scrollback: &'static Mutex<Scrollback>,
+    cursor_enabled: bool,
+    cursor_shape: CursorShape,
+    pointer: Option<(usize, usize, ScreenChar)>,                                                    // Célula sob o ponteiro do mouse e o seu conteúdo original
+    backing: Option<&'static mut Buffer>,                                                           // Buffer próprio do console ativo, enquanto ele escreve no VGA
     buffer: &'static mut Buffer,                                                                    // Static define que a lifetime deve ser uma referência válida por toda a duração do programa.
 }
 
 impl Writer {
+    // Cria o Writer do console index, apenas o console 0 começa exibido na tela.
+    fn new(index: usize) -> Writer {
+        /* O bloco unsafe é necessário, pois o compilador Rust não pode provar que os ponteiros brutos
+        * que criamos são válidos. Cada console recebe o seu próprio buffer, que é acessado apenas
+        * através do seu Writer.
+        */
+        let backing = unsafe { &mut *(core::ptr::addr_of_mut!(CONSOLE_BUFFERS[index]) as *mut Buffer) };
+        let (buffer, backing) = if index == 0 {
+            /*
+             * O console ativo aponta para o buffer VGA em 0xb8000
+             * "as *mut Buffer" Converte esse endereço literal para um ponteiro mutável para um tipo Buffer.
+             */
+            (unsafe { &mut *(0xb8000 as *mut Buffer) }, Some(backing))
+        } else {
+            (backing, None)
+        };
+        let mut writer = Writer {
+            column_position: 0,
+            row_position: BUFFER_HEIGHT - 1,                                                        // Por padrão o texto é escrito na última linha e sobe com a rolagem
+            color_code: ColorCode::new(Color::Yellow, Color::Black),
+            default_color_code: ColorCode::new(Color::Yellow, Color::Black),
+            bold: false,
+            saved_position: (BUFFER_HEIGHT - 1, 0),
+            scroll_top: 0,
+            scroll_bottom: BUFFER_HEIGHT - 1,
+            escape: EscapeParser::new(),
+            region: Region::FULL_SCREEN,
+            view_offset: 0,
+            scrollback: &SCROLLBACKS[index],
+            cursor_enabled: true,
+            cursor_shape: CursorShape::Underline,
+            pointer: None,
+            backing,
+            buffer,
+        };
+        if index != 0 {
+            for row in 0..BUFFER_HEIGHT {
+                writer.clear_row(row);
+            }
+        }
+        writer
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.backing.is_some()
+    }
+
+    /* Desenha o ponteiro do mouse invertendo as cores de uma célula, guardando o caractere original.
+    * O ponteiro é escondido antes de qualquer alteração da tela e redesenhado depois dela, então a
+    * saída, a rolagem e a troca de console não corrompem a célula.
+    */
+    fn show_pointer(&mut self, row: usize, col: usize) {
+        self.hide_pointer();
+        if !self.is_active() || row >= BUFFER_HEIGHT || col >= BUFFER_WIDTH {
+            return;
+        }
+        let saved = self.buffer.chars[row][col].read();
+        self.buffer.chars[row][col].write(ScreenChar { color_code: saved.color_code.inverted(), ..saved });
+        self.pointer = Some((row, col, saved));
+    }
+
+    // Restaura a célula sob o ponteiro e retorna a posição em que ele estava.
+    fn hide_pointer(&mut self) -> Option<(usize, usize)> {
+        let (row, col, saved) = self.pointer.take()?;
+        self.buffer.chars[row][col].write(saved);
+        Some((row, col))
+    }
+
+    fn restore_pointer(&mut self, pointer: Option<(usize, usize)>) {
+        if let Some((row, col)) = pointer {
+            self.show_pointer(row, col);
+        }
+    }
+
     pub fn write_byte(&mut self, byte: u8) {
+        let pointer = self.hide_pointer();
+        self.restore_live_view();
+        self.put_byte(byte);
+        self.update_cursor();
+        self.restore_pointer(pointer);
+    }
+
+    // Escreve o byte sem mover o cursor de hardware, que é atualizado uma vez ao final de cada escrita.
+    fn put_byte(&mut self, byte: u8) {
         match byte {
             b'\n' => self.new_line(),
+            b'\r' => self.column_position = 0,
+            b'\t' => {
+                let next_stop = (self.column_position / TAB_WIDTH + 1) * TAB_WIDTH;
+                while self.column_position < next_stop.min(BUFFER_WIDTH) {
+                    self.put_byte(b' ');
+                }
+            }
+            0x08 => self.backspace(),
             byte => {
                 if self.column_position >= BUFFER_WIDTH {
                     self.new_line();
                 }
 
-                let row = BUFFER_HEIGHT - 1;
+                let row = self.row_position;
                 let col = self.column_position;
 
                 let color_code = self.color_code;
@@ -103,92 +220,2120 @@ impl Writer {
     }
 
     pub fn write_string(&mut self, s: &str) {
-        for byte in s.bytes() {
-            match byte {                                                                            // As strings em Rust são UTF-8 e, por padrão, podem conter bytes não suportados pelo buffer de texto VGA.
-                0x20..0x7e | b'\n' => self.write_byte(byte),                                     // Caractere ASCII válido ou nova linha
-                _ => self.write_byte(0xfe),                                                    // Caractere ASCII inválido
-            }
+        let pointer = self.hide_pointer();
+        self.restore_live_view();
+        for character in s.chars() {                                                                // As strings em Rust são UTF-8, logo cada caractere pode ocupar de 1 a 4 bytes.
+            self.put_char(character);
+        }
+        self.update_cursor();
+        self.restore_pointer(pointer);
+    }
+
+    // Backspace apenas move o cursor para a esquerda, como nos terminais, voltando para a linha anterior.
+    fn backspace(&mut self) {
+        if self.column_position > 0 {
+            self.column_position -= 1;
+        } else if self.row_position > self.region.top {
+            self.row_position -= 1;
+            self.column_position = BUFFER_WIDTH - 1;
+        }
+    }
+
+    // Apaga a região do Writer e volta o cursor para o seu canto superior esquerdo.
+    pub fn clear_screen(&mut self) {
+        let pointer = self.hide_pointer();
+        self.restore_live_view();
+        for row in self.region.rows() {
+            self.clear_row(row);
+        }
+        self.row_position = self.region.top;
+        self.column_position = 0;
+        self.update_cursor();
+        self.restore_pointer(pointer);
+    }
+
+    // Apaga da posição atual até o fim da linha, sem mover o cursor.
+    pub fn clear_to_end_of_line(&mut self) {
+        let pointer = self.hide_pointer();
+        self.restore_live_view();
+        self.clear_cells(self.row_position, self.column_position.min(BUFFER_WIDTH), BUFFER_WIDTH);
+        self.restore_pointer(pointer);
+    }
+
+    pub fn column_position(&self) -> usize {
+        self.column_position
+    }
+
+    // Posição de escrita como (linha, coluna).
+    pub fn position(&self) -> (usize, usize) {
+        (self.row_position, self.column_position)
+    }
+
+    // Move a posição de escrita, limitando-a à região do Writer. As linhas são as da tela.
+    pub fn set_position(&mut self, row: usize, col: usize) {
+        self.row_position = row.clamp(self.region.top, self.region.bottom());
+        self.column_position = col.min(BUFFER_WIDTH - 1);
+        self.update_cursor();
+    }
+
+    // Define a cor global do Writer, que também passa a ser a cor restaurada pelo reset do ANSI.
+    pub fn set_color(&mut self, foreground: Color, background: Color) {
+        self.color_code = ColorCode::new(foreground, background);
+        self.default_color_code = self.color_code;
+        self.bold = false;
+    }
+
+    /* Limita a rolagem às linhas de top a bottom (inclusivas), as linhas fora da região permanecem
+    * fixas na tela. Regiões inválidas ou fora da região do Writer são ignoradas.
+    */
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        if top >= self.region.top && top < bottom && bottom <= self.region.bottom() {
+            self.scroll_top = top;
+            self.scroll_bottom = bottom;
+        }
+    }
+
+    pub fn reset_scroll_region(&mut self) {
+        self.scroll_top = self.region.top;
+        self.scroll_bottom = self.region.bottom();
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /* Restringe o Writer a uma faixa de linhas, deixando as demais para uma linha de status ou para
+    * outras panes. A região de rolagem passa a ser a região inteira e a posição de escrita é movida
+    * para dentro dela. Retorna false se a região não cabe na tela.
+    */
+    pub fn set_region(&mut self, region: Region) -> bool {
+        if !region.is_valid() {
+            return false;
+        }
+        self.restore_live_view();
+        self.region = region;
+        self.reset_scroll_region();
+        let clamp = |row: usize| row.clamp(region.top, region.bottom());
+        self.saved_position.0 = clamp(self.saved_position.0);
+        self.set_position(clamp(self.row_position), self.column_position);
+        true
+    }
+
+    // Escreve com outras cores apenas nessa chamada, mantendo a cor global do Writer.
+    pub fn write_colored(&mut self, s: &str, foreground: Color, background: Color) {
+        let color_code = core::mem::replace(&mut self.color_code, ColorCode::new(foreground, background));
+        self.write_string(s);
+        self.color_code = color_code;
+    }
+
+    // Escreve em uma posição da tela e restaura a posição de escrita anterior.
+    pub fn write_at(&mut self, row: usize, col: usize, s: &str) {
+        let position = self.position();
+        self.set_position(row, col);
+        self.write_string(s);
+        self.set_position(position.0, position.1);
+    }
+
+    pub fn write_at_colored(&mut self, row: usize, col: usize, s: &str, foreground: Color, background: Color) {
+        let color_code = core::mem::replace(&mut self.color_code, ColorCode::new(foreground, background));
+        self.write_at(row, col, s);
+        self.color_code = color_code;
+    }
+
+    // Escreve um caractere Unicode, convertendo-o para a página de código 437 ou para a fonte carregada.
+    pub fn write_char(&mut self, character: char) {
+        let pointer = self.hide_pointer();
+        self.restore_live_view();
+        self.put_char(character);
+        self.update_cursor();
+        self.restore_pointer(pointer);
+    }
+
+    fn put_char(&mut self, character: char) {
+        if character == '\u{1b}' || (self.escape.in_sequence() && character.is_ascii()) {
+            self.escape_byte(character as u8);
+            return;
         }
+        self.escape.reset();                                                                        // Um caractere não ASCII interrompe a sequência de escape incompleta
+        match character {
+            '\n' | '\r' | '\t' | '\u{8}' => self.put_byte(character as u8),
+            _ => self.put_byte(text_glyph(character)),
+        }
+    }
+
+    /* Move o cursor de hardware para a posição de escrita. Quando a linha está cheia o próximo
+    * caractere vai para uma nova linha, mas o cursor permanece na última coluna.
+    */
+    pub fn update_cursor(&self) {
+        if !self.is_active() {
+            return;
+        }
+        let col = self.column_position.min(BUFFER_WIDTH - 1);
+        let position = (self.row_position * BUFFER_WIDTH + col) as u16;
+        crtc_write(CRTC_CURSOR_LOCATION_HIGH, (position >> 8) as u8);
+        crtc_write(CRTC_CURSOR_LOCATION_LOW, position as u8);
+    }
+
+    pub fn enable_cursor(&mut self) {
+        self.cursor_enabled = true;
+        self.apply_cursor();
+    }
+
+    pub fn disable_cursor(&mut self) {
+        self.cursor_enabled = false;
+        self.apply_cursor();
+    }
+
+    // Define as linhas de varredura (scanlines) ocupadas pelo cursor dentro da célula.
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        self.cursor_shape = shape;
+        self.apply_cursor();
+    }
+
+    // Programa o cursor de hardware com o estado do console, que é guardado mesmo quando ele está inativo.
+    fn apply_cursor(&self) {
+        if !self.is_active() {
+            return;
+        }
+        let (start, end) = self.cursor_shape.scanlines();
+        let disable = if self.cursor_enabled { 0 } else { CURSOR_DISABLE };
+        let start_register = crtc_read(CRTC_CURSOR_START) & !(CURSOR_SCANLINE_MASK | CURSOR_DISABLE);
+        let end_register = crtc_read(CRTC_CURSOR_END) & !CURSOR_SCANLINE_MASK;
+        crtc_write(CRTC_CURSOR_START, start_register | disable | (start & CURSOR_SCANLINE_MASK));
+        crtc_write(CRTC_CURSOR_END, end_register | (end & CURSOR_SCANLINE_MASK));
+        self.update_cursor();
+    }
+
+    // Copia a tela para o buffer próprio do console e devolve a memória do VGA.
+    fn deactivate(&mut self) -> &'static mut Buffer {
+        self.hide_pointer();
+        self.restore_live_view();
+        let backing = self.backing.take().expect("console ja esta inativo");
+        copy_buffer(self.buffer, backing);
+        core::mem::replace(&mut self.buffer, backing)
     }
 
+    // Passa a escrever na memória do VGA, exibindo o conteúdo do buffer próprio do console.
+    fn activate(&mut self, vga: &'static mut Buffer) {
+        let backing = core::mem::replace(&mut self.buffer, vga);
+        copy_buffer(backing, self.buffer);
+        self.backing = Some(backing);
+        self.apply_cursor();
+    }
+
+    // Avança para a próxima linha, rolando a região de rolagem quando a escrita está na sua última linha.
     fn new_line(&mut self) {
-        for row in 1..BUFFER_HEIGHT {
+        if self.row_position == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.row_position < self.region.bottom() {
+            self.row_position += 1;
+        }
+        self.column_position = 0;
+    }
+
+    // Sobe as linhas da região de rolagem, apagando as linhas que surgem no final.
+    fn scroll_up(&mut self, lines: usize) {
+        let lines = lines.min(self.scroll_bottom - self.scroll_top + 1);
+        if self.scroll_top == self.region.top {                                                     // Apenas as linhas que saem pelo topo da região vão para o histórico
+            let mut scrollback = self.scrollback.lock();
+            for row in self.scroll_top..self.scroll_top + lines {
+                scrollback.push(self.read_row(row));
+            }
+        }
+        self.move_rows_up(self.scroll_top, self.scroll_bottom, lines);
+        for row in self.scroll_bottom + 1 - lines..=self.scroll_bottom {
+            self.clear_row(row);
+        }
+    }
+
+    /* Copia as linhas de top + lines a bottom para cima, sem apagar as últimas linhas. Quando lines
+    * alcança a altura da faixa nenhuma linha permanece, então não há o que copiar.
+    */
+    fn move_rows_up(&mut self, top: usize, bottom: usize, lines: usize) {
+        let last = match bottom.checked_sub(lines) {
+            Some(last) => last,
+            None => return,
+        };
+        for row in top..=last {
             for col in 0..BUFFER_WIDTH {
-                let character = self.buffer.chars[row][col].read();
-                self.buffer.chars[row-1][col].write(character);
+                let character = self.buffer.chars[row + lines][col].read();
+                self.buffer.chars[row][col].write(character);
             }
         }
-        self.clear_row(BUFFER_HEIGHT - 1);
-        self.column_position = 0;
+    }
+
+    // Desce as linhas da região de rolagem, apagando as linhas que surgem no início.
+    fn scroll_down(&mut self, lines: usize) {
+        let lines = lines.min(self.scroll_bottom - self.scroll_top + 1);
+        for row in (self.scroll_top + lines..=self.scroll_bottom).rev() {
+            for col in 0..BUFFER_WIDTH {
+                let character = self.buffer.chars[row - lines][col].read();
+                self.buffer.chars[row][col].write(character);
+            }
+        }
+        for row in self.scroll_top..self.scroll_top + lines {
+            self.clear_row(row);
+        }
     }
 
     fn clear_row(&mut self, row: usize) {
+        self.clear_cells(row, 0, BUFFER_WIDTH);
+    }
+
+    fn read_row(&self, row: usize) -> [ScreenChar; BUFFER_WIDTH] {
+        let mut cells = [BLANK; BUFFER_WIDTH];
+        for (cell, screen_char) in cells.iter_mut().zip(self.buffer.chars[row].iter()) {
+            *cell = screen_char.read();
+        }
+        cells
+    }
+
+    // Converte os bytes da tela, do histórico e da célula sob o ponteiro de acordo com a tabela.
+    fn translate_glyphs(&mut self, table: &[u8; 256]) {
+        let translate = |screen_char: ScreenChar| ScreenChar {
+            ascii_character: table[usize::from(screen_char.ascii_character)],
+            ..screen_char
+        };
+        for cell in self.buffer.chars.iter_mut().flat_map(|row| row.iter_mut()) {
+            cell.write(translate(cell.read()));
+        }
+        if let Some((_, _, saved)) = self.pointer.as_mut() {
+            *saved = translate(*saved);
+        }
+        let mut scrollback = self.scrollback.lock();
+        let scrollback = &mut *scrollback;
+        for cell in scrollback.rows.iter_mut().chain(scrollback.live.iter_mut()).flat_map(|row| row.iter_mut()) {
+            *cell = translate(*cell);
+        }
+    }
+
+    // Bytes dos caracteres de uma linha da tela, sem as cores, utilizado pelos testes de outros módulos.
+    #[cfg(test)]
+    pub(crate) fn row_bytes(&self, row: usize) -> [u8; BUFFER_WIDTH] {
+        self.read_row(row).map(|cell| cell.ascii_character)
+    }
+
+    pub fn is_scrolled_back(&self) -> bool {
+        self.view_offset != 0
+    }
+
+    /* Volta a visualização em direção às linhas mais antigas do histórico. Ao sair da visão ao vivo
+    * a tela atual é copiada para o histórico, para ser restaurada quando a visualização voltar.
+    */
+    pub fn scroll_view_up(&mut self, lines: usize) {
+        let pointer = self.hide_pointer();
+        let mut scrollback = self.scrollback.lock();
+        let offset = (self.view_offset + lines).min(scrollback.len);
+        if offset != self.view_offset {
+            if self.view_offset == 0 {
+                for row in 0..BUFFER_HEIGHT {
+                    scrollback.live[row] = self.read_row(row);
+                }
+            }
+            self.view_offset = offset;
+            self.render_view(&scrollback);
+        }
+        drop(scrollback);
+        self.restore_pointer(pointer);
+    }
+
+    // Avança a visualização em direção à tela atual.
+    pub fn scroll_view_down(&mut self, lines: usize) {
+        if self.view_offset == 0 {
+            return;
+        }
+        let pointer = self.hide_pointer();
+        let scrollback = self.scrollback.lock();
+        self.view_offset = self.view_offset.saturating_sub(lines);
+        self.render_view(&scrollback);
+        drop(scrollback);
+        self.restore_pointer(pointer);
+    }
+
+    // Volta para a visão ao vivo, chamada antes de qualquer escrita na tela.
+    pub fn restore_live_view(&mut self) {
+        self.scroll_view_down(self.view_offset);
+    }
+
+    // Exibe o histórico apenas na região do Writer, as demais linhas continuam ao vivo.
+    fn render_view(&mut self, scrollback: &Scrollback) {
+        let first = scrollback.len - self.view_offset;
+        for row in 0..self.region.height {
+            let line = first + row;
+            let cells = if line < scrollback.len {
+                scrollback.row(line)
+            } else {
+                &scrollback.live[self.region.top + line - scrollback.len]
+            };
+            for (screen_char, &cell) in self.buffer.chars[self.region.top + row].iter_mut().zip(cells.iter()) {
+                screen_char.write(cell);
+            }
+        }
+    }
+
+    fn clear_cells(&mut self, row: usize, start: usize, end: usize) {
         let blank = ScreenChar{ ascii_character: b' ', color_code: self.color_code };
-        for col in 0..BUFFER_WIDTH {
-            self.buffer.chars[row][col].write(blank);
+        self.fill_cells(row, start, end, blank);
+    }
+
+    fn fill_cells(&mut self, row: usize, start: usize, end: usize, screen_char: ScreenChar) {
+        for col in start..end {
+            self.buffer.chars[row][col].write(screen_char);
         }
     }
-}
 
-impl fmt::Write for Writer {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.write_string(s);
-        Ok(())
+    fn escape_byte(&mut self, byte: u8) {
+        match self.escape.advance(byte) {
+            EscapeAction::None => {}
+            EscapeAction::Control(byte) => match byte {
+                b'\n' | b'\r' | b'\t' | 0x08 => self.put_byte(byte),
+                _ => {}                                                                             // Demais caracteres de controle (como o BEL) são ignorados
+            },
+            EscapeAction::Escape(b'7') => self.saved_position = self.position(),
+            EscapeAction::Escape(b'8') => self.set_position(self.saved_position.0, self.saved_position.1),
+            EscapeAction::Escape(_) => {}
+            EscapeAction::Csi(sequence) => self.execute_csi(&sequence),
+        }
     }
-}
 
+    /* Executa uma sequência CSI. Os parâmetros de movimento valem 1 quando omitidos ou zero, e as
+    * linhas e colunas das sequências começam em 1 no topo da região, enquanto as do Writer começam em
+    * 0 no topo da tela.
+    */
+    fn execute_csi(&mut self, sequence: &CsiSequence) {
+        let (row, col) = self.position();
+        let top = self.region.top;
+        let count = sequence.param(0, 1);
+        if sequence.private {
+            match (sequence.command, sequence.param(0, 0)) {
+                (b'h', 25) => self.enable_cursor(),                                                 // Exibe e esconde o cursor (DECTCEM)
+                (b'l', 25) => self.disable_cursor(),
+                _ => {}
+            }
+            return;
+        }
+        match sequence.command {
+            b'A' => self.set_position(row.saturating_sub(count), col),
+            b'B' => self.set_position(row.saturating_add(count), col),
+            b'C' => self.set_position(row, col.saturating_add(count)),
+            b'D' => self.set_position(row, col.saturating_sub(count)),
+            b'E' => self.set_position(row.saturating_add(count), 0),
+            b'F' => self.set_position(row.saturating_sub(count), 0),
+            b'G' => self.set_position(row, count - 1),
+            b'd' => self.set_position(top.saturating_add(count - 1), col),
+            b'H' | b'f' => self.set_position(top.saturating_add(count - 1), sequence.param(1, 1) - 1),
+            b'J' => self.erase_in_display(sequence.param(0, 0)),
+            b'K' => self.erase_in_line(sequence.param(0, 0)),
+            b'S' => self.scroll_up(count),
+            b'T' => self.scroll_down(count),
+            b'm' => self.select_graphic_rendition(sequence.params()),
+            b'r' => {
+                self.set_scroll_region(top.saturating_add(count - 1), top.saturating_add(sequence.param(1, self.region.height) - 1));
+                self.set_position(top, 0);
+            }
+            b's' => self.saved_position = self.position(),
+            b'u' => self.set_position(self.saved_position.0, self.saved_position.1),
+            _ => {}
+        }
+    }
 
-// Semelhante a macro print, porém imprime no buffer VGA
-#[macro_export]
-macro_rules! print {
-    ($($arg:tt)*) => ($crate::vga_buffer::_print(format_args!($($arg)*)));
-}
+    // 0 apaga do cursor até o fim da região, 1 do início da região até o cursor e 2 a região inteira.
+    fn erase_in_display(&mut self, mode: usize) {
+        let (row, _) = self.position();
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                for row in row + 1..=self.region.bottom() {
+                    self.clear_row(row);
+                }
+            }
+            1 => {
+                for row in self.region.top..row {
+                    self.clear_row(row);
+                }
+                self.erase_in_line(1);
+            }
+            2 => {
+                for row in self.region.rows() {
+                    self.clear_row(row);
+                }
+            }
+            _ => {}
+        }
+    }
 
-// Semelhante a macro println, porém imprime no buffer VGA
-#[macro_export]
-macro_rules! println {
-    () => ($crate::print!("\n"));
-    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+    // 0 apaga do cursor até o fim da linha, 1 do início da linha até o cursor e 2 a linha inteira.
+    fn erase_in_line(&mut self, mode: usize) {
+        let (row, col) = self.position();
+        match mode {
+            0 => self.clear_to_end_of_line(),
+            1 => self.clear_cells(row, 0, (col + 1).min(BUFFER_WIDTH)),
+            2 => self.clear_row(row),
+            _ => {}
+        }
+    }
+
+    /* Aplica os atributos SGR (Select Graphic Rendition). As 8 cores do ANSI correspondem às 8 cores
+    * escuras do VGA e o negrito é exibido com a versão clara da cor, já que o modo texto não possui
+    * fonte em negrito.
+    */
+    fn select_graphic_rendition(&mut self, params: &[usize]) {
+        let default_foreground = self.default_color_code.0 & 0x0f;
+        let default_background = self.default_color_code.0 >> 4;
+        let mut foreground = self.color_code.0 & 0x0f;
+        let mut background = self.color_code.0 >> 4;
+        let params = if params.is_empty() { &[0][..] } else { params };                             // "ESC [ m" equivale a "ESC [ 0 m"
+
+        for &param in params {
+            match param {
+                0 => {
+                    self.bold = false;
+                    foreground = default_foreground;
+                    background = default_background;
+                }
+                1 => {
+                    self.bold = true;
+                    foreground |= BRIGHT;
+                }
+                22 => {
+                    if self.bold {
+                        foreground &= !BRIGHT;
+                    }
+                    self.bold = false;
+                }
+                30..=37 => foreground = ANSI_COLORS[param - 30] as u8 | if self.bold { BRIGHT } else { 0 },
+                39 => foreground = default_foreground,
+                40..=47 => background = ANSI_COLORS[param - 40] as u8,
+                49 => background = default_background,
+                90..=97 => foreground = ANSI_COLORS[param - 90] as u8 | BRIGHT,
+                100..=107 => background = ANSI_COLORS[param - 100] as u8 | BRIGHT,
+                _ => {}
+            }
+        }
+        self.color_code = ColorCode(background << 4 | foreground);
+    }
 }
 
-// Imprime a string formatada no buffer do VGA através da instancia global WRITER
-#[doc(hidden)]
-pub fn _print(args: fmt::Arguments) {
-    use core::fmt::Write;
-    WRITER.lock().write_fmt(args).unwrap();
+/* Faixa de linhas da tela, com a largura inteira. O Writer de cada console escreve e rola apenas na
+* sua região, e as linhas restantes podem ser divididas entre panes, como a linha de status, cada uma
+* com a sua própria rolagem.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub top: usize,
+    pub height: usize,
 }
 
+impl Region {
+    pub const FULL_SCREEN: Region = Region { top: 0, height: BUFFER_HEIGHT };
 
-#[test_case]
-fn test_println_simple() {
-    println!("test_println_simple output");
-}
+    pub const fn new(top: usize, height: usize) -> Region {
+        Region { top, height }
+    }
 
-#[test_case]
-fn test_println_many() {
-    for _ in 0..200 {
-        println!("test_println_many output");
+    // Última linha da região, inclusiva.
+    pub fn bottom(&self) -> usize {
+        self.top + self.height - 1
     }
-}
 
-#[test_case]
-fn test_println_output() {
-    /* A função define uma string e itera sobre ela e os caracteres impressos no buffer e compara.
-     * Como o println coloca uma linha nova, a string deve estar na linha anterior.
-    */
-    let s = "Some test string that fits on a single line";
-    println!("{}", s);
-    for (i, c) in s.chars().enumerate() {
-        let screen_char = WRITER.lock().buffer.chars[BUFFER_HEIGHT - 2][i].read();
-        assert_eq!(char::from(screen_char.ascii_character), c);
+    pub fn rows(&self) -> core::ops::Range<usize> {
+        self.top..self.top + self.height
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.height > 0 && self.top + self.height <= BUFFER_HEIGHT
     }
+
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.top < other.top + other.height && other.top < self.top + self.height
+    }
+}
+
+/* Área de texto em uma região da tela de um console, com posição, cor e rolagem próprias. As panes
+* escrevem no mesmo buffer que o Writer do console, então a região do Writer deve ser reduzida para não
+* incluir as linhas da pane. Ao contrário do Writer, as panes não interpretam sequências ANSI nem
+* guardam histórico.
+*/
+pub struct Pane {
+    console: usize,
+    region: Region,
+    row: usize,                                                                                     // Posição relativa ao topo da região
+    column: usize,
+    color_code: ColorCode,
 }
 
+impl Pane {
+    // Retorna None se o console não existe ou a região não cabe na tela.
+    pub fn new(console: usize, region: Region) -> Option<Pane> {
+        if console >= CONSOLE_COUNT || !region.is_valid() {
+            return None;
+        }
+        Some(Pane { console, region, row: 0, column: 0, color_code: ColorCode::new(Color::White, Color::Black) })
+    }
+
+    pub fn console(&self) -> usize {
+        self.console
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_color(&mut self, foreground: Color, background: Color) {
+        self.color_code = ColorCode::new(foreground, background);
+    }
+
+    // Move a posição de escrita, com a linha relativa ao topo da região.
+    pub fn set_position(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.region.height - 1);
+        self.column = col.min(BUFFER_WIDTH - 1);
+    }
+
+    // Apaga a região com a cor da pane e volta para o seu canto superior esquerdo.
+    pub fn clear(&mut self) {
+        let blank = self.blank();
+        self.with_writer(|pane, writer| {
+            for row in pane.region.rows() {
+                writer.fill_cells(row, 0, BUFFER_WIDTH, blank);
+            }
+        });
+        self.row = 0;
+        self.column = 0;
+    }
+
+    pub fn clear_to_end_of_line(&mut self) {
+        let blank = self.blank();
+        self.with_writer(|pane, writer| {
+            writer.fill_cells(pane.region.top + pane.row, pane.column.min(BUFFER_WIDTH), BUFFER_WIDTH, blank);
+        });
+    }
+
+    pub fn write_string(&mut self, s: &str) {
+        self.with_writer(|pane, writer| {
+            for character in s.chars() {
+                pane.put_char(writer, character);
+            }
+        });
+    }
+
+    fn put_char(&mut self, writer: &mut Writer, character: char) {
+        match character {
+            '\n' => self.new_line(writer),
+            '\r' => self.column = 0,
+            _ => {
+                if self.column >= BUFFER_WIDTH {
+                    self.new_line(writer);
+                }
+                let screen_char = ScreenChar { ascii_character: text_glyph(character), color_code: self.color_code };
+                writer.buffer.chars[self.region.top + self.row][self.column].write(screen_char);
+                self.column += 1;
+            }
+        }
+    }
 
+    // Rola apenas as linhas da pane quando a escrita está na sua última linha.
+    fn new_line(&mut self, writer: &mut Writer) {
+        if self.row + 1 < self.region.height {
+            self.row += 1;
+        } else {
+            let region = self.region;
+            writer.move_rows_up(region.top, region.bottom(), 1);
+            writer.fill_cells(region.bottom(), 0, BUFFER_WIDTH, self.blank());
+        }
+        self.column = 0;
+    }
 
+    fn blank(&self) -> ScreenChar {
+        ScreenChar { ascii_character: b' ', color_code: self.color_code }
+    }
 
+    fn with_writer(&mut self, f: impl FnOnce(&mut Pane, &mut Writer)) {
+        use x86_64::instructions::interrupts;
 
+        interrupts::without_interrupts(|| {
+            let mut writer = CONSOLES[self.console].lock();
+            let pointer = writer.hide_pointer();
+            f(self, &mut writer);
+            writer.restore_pointer(pointer);
+        });
+    }
+}
 
+impl fmt::Write for Pane {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
+
+/* Histórico das linhas que saíram pelo topo da região do Writer, guardado em um buffer circular de
+* tamanho fixo já que o kernel não possui alocação dinâmica. O limite de linhas pode ser reduzido com
+* a opção "scrollback" da linha de comando.
+*/
+pub const SCROLLBACK_CAPACITY: usize = 256;
+const BLANK: ScreenChar = ScreenChar { ascii_character: 0, color_code: ColorCode(0) };
+
+struct Scrollback {
+    rows: [[ScreenChar; BUFFER_WIDTH]; SCROLLBACK_CAPACITY],
+    start: usize,                                                                                   // Índice da linha mais antiga
+    len: usize,
+    limit: usize,
+    live: [[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT],                                              // Cópia da tela atual enquanto o histórico é exibido
+}
+
+impl Scrollback {
+    const fn new() -> Scrollback {
+        Scrollback {
+            rows: [[BLANK; BUFFER_WIDTH]; SCROLLBACK_CAPACITY],
+            start: 0,
+            len: 0,
+            limit: SCROLLBACK_CAPACITY,
+            live: [[BLANK; BUFFER_WIDTH]; BUFFER_HEIGHT],
+        }
+    }
+
+    // Guarda uma linha, descartando a mais antiga quando o limite é atingido.
+    fn push(&mut self, row: [ScreenChar; BUFFER_WIDTH]) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.len < self.limit {
+            self.rows[(self.start + self.len) % self.limit] = row;
+            self.len += 1;
+        } else {
+            self.rows[self.start] = row;
+            self.start = (self.start + 1) % self.limit;
+        }
+    }
+
+    // Linha do histórico a partir da mais antiga.
+    fn row(&self, index: usize) -> &[ScreenChar; BUFFER_WIDTH] {
+        &self.rows[(self.start + index) % self.limit]
+    }
+
+    fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.min(SCROLLBACK_CAPACITY);
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+const BRIGHT: u8 = 1 << 3;                                                                          // Bit que transforma uma cor escura do VGA na sua versão clara
+
+// Cores do ANSI na ordem dos códigos 30 a 37: preto, vermelho, verde, amarelo, azul, magenta, ciano e branco.
+const ANSI_COLORS: [Color; 8] = [
+    Color::Black, Color::Red, Color::Green, Color::Brown, Color::Blue, Color::Magenta, Color::Cyan, Color::LightGray,
+];
+
+/* Interpretador das sequências de escape ANSI/VT100 como uma máquina de estados alimentada byte a
+* byte. Uma sequência CSI tem a forma "ESC [ parâmetros final", com os parâmetros numéricos
+* separados por ";" e um caractere final entre 0x40 e 0x7e que identifica o comando.
+*/
+pub const ESC: u8 = 0x1b;
+const MAX_CSI_PARAMS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    Ground,
+    Escape,
+    Csi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsiSequence {
+    params: [usize; MAX_CSI_PARAMS],
+    len: usize,
+    pub private: bool,                                                                              // Sequências privadas do DEC, iniciadas por "?"
+    pub command: u8,
+}
+
+impl CsiSequence {
+    pub fn params(&self) -> &[usize] {
+        &self.params[..self.len]
+    }
+
+    // Retorna o parâmetro, utilizando o valor padrão quando ele é omitido ou zero.
+    pub fn param(&self, index: usize, default: usize) -> usize {
+        match self.params().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeAction {
+    None,
+    Control(u8),                                                                                    // Caractere de controle recebido no meio de uma sequência
+    Escape(u8),                                                                                     // Sequência de dois caracteres, como "ESC 7"
+    Csi(CsiSequence),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EscapeParser {
+    state: EscapeState,
+    params: [usize; MAX_CSI_PARAMS],
+    count: usize,
+    private: bool,
+}
+
+impl EscapeParser {
+    pub const fn new() -> EscapeParser {
+        EscapeParser { state: EscapeState::Ground, params: [0; MAX_CSI_PARAMS], count: 0, private: false }
+    }
+
+    pub fn in_sequence(&self) -> bool {
+        self.state != EscapeState::Ground
+    }
+
+    pub fn reset(&mut self) {
+        self.state = EscapeState::Ground;
+    }
+
+    pub fn advance(&mut self, byte: u8) -> EscapeAction {
+        match (self.state, byte) {
+            (_, ESC) => {
+                self.state = EscapeState::Escape;
+                EscapeAction::None
+            }
+            (_, 0x18) | (_, 0x1a) => {                                                              // CAN e SUB cancelam a sequência
+                self.state = EscapeState::Ground;
+                EscapeAction::None
+            }
+            (EscapeState::Ground, byte) => EscapeAction::Control(byte),
+            (_, 0x00..=0x1f) => EscapeAction::Control(byte),
+            (EscapeState::Escape, b'[') => {
+                self.state = EscapeState::Csi;
+                self.params = [0; MAX_CSI_PARAMS];
+                self.count = 0;
+                self.private = false;
+                EscapeAction::None
+            }
+            (EscapeState::Escape, byte) => {
+                self.state = EscapeState::Ground;
+                EscapeAction::Escape(byte)
+            }
+            (EscapeState::Csi, b'0'..=b'9') => {
+                self.count = self.count.max(1);
+                if let Some(param) = self.params.get_mut(self.count - 1) {                          // Parâmetros além do limite são descartados
+                    *param = param.saturating_mul(10).saturating_add(usize::from(byte - b'0'));
+                }
+                EscapeAction::None
+            }
+            (EscapeState::Csi, b';') => {
+                self.count = (self.count.max(1) + 1).min(MAX_CSI_PARAMS + 1);
+                EscapeAction::None
+            }
+            (EscapeState::Csi, b'<'..=b'?') => {
+                self.private = true;
+                EscapeAction::None
+            }
+            (EscapeState::Csi, 0x40..=0x7e) => {
+                self.state = EscapeState::Ground;
+                EscapeAction::Csi(CsiSequence {
+                    params: self.params,
+                    len: self.count.min(MAX_CSI_PARAMS),
+                    private: self.private,
+                    command: byte,
+                })
+            }
+            (EscapeState::Csi, _) => EscapeAction::None,                                            // Caracteres intermediários não são suportados e são ignorados
+        }
+    }
+}
+
+impl Default for EscapeParser {
+    fn default() -> Self {
+        EscapeParser::new()
+    }
+}
+
+/* O cursor piscante é desenhado pelo próprio hardware e controlado pelos registradores do CRT
+* Controller, acessados escrevendo o índice do registrador na porta 0x3d4 e o valor na porta 0x3d5.
+* A posição é o índice da célula no buffer (linha * 80 + coluna) e o formato é definido pelas linhas
+* de varredura inicial e final do cursor dentro do caractere de 16 linhas.
+*/
+const CRTC_INDEX_PORT: u16 = 0x3d4;
+const CRTC_DATA_PORT: u16 = 0x3d5;
+const CRTC_CURSOR_START: u8 = 0x0a;
+const CRTC_CURSOR_END: u8 = 0x0b;
+const CRTC_CURSOR_LOCATION_HIGH: u8 = 0x0e;
+const CRTC_CURSOR_LOCATION_LOW: u8 = 0x0f;
+const CURSOR_DISABLE: u8 = 1 << 5;
+const CURSOR_SCANLINE_MASK: u8 = 0x1f;
+
+fn crtc_write(index: u8, value: u8) {
+    unsafe {
+        Port::new(CRTC_INDEX_PORT).write(index);
+        Port::new(CRTC_DATA_PORT).write(value);
+    }
+}
+
+fn crtc_read(index: u8) -> u8 {
+    unsafe {
+        Port::new(CRTC_INDEX_PORT).write(index);
+        Port::new(CRTC_DATA_PORT).read()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Underline,                                                                                      // Padrão da BIOS, nas duas últimas linhas da célula
+    Block,
+    Scanlines(u8, u8),
+}
+
+impl CursorShape {
+    fn scanlines(self) -> (u8, u8) {
+        match self {
+            CursorShape::Underline => (14, 15),
+            CursorShape::Block => (0, 15),
+            CursorShape::Scanlines(start, end) => (start, end),
+        }
+    }
+}
+
+/* O modo texto do VGA utiliza a página de código 437 do IBM PC. Além do ASCII, ela possui símbolos
+* nos bytes 0x01 a 0x1f e 0x7f, e letras acentuadas, caracteres de desenho de caixas, blocos, letras
+* gregas e símbolos matemáticos nos bytes 0x80 a 0xff. As tabelas abaixo contêm o caractere Unicode
+* de cada byte, na ordem da página.
+*/
+const FALLBACK_GLYPH: u8 = 0xfe;                                                                    // Quadrado exibido no lugar dos caracteres sem representação
+
+const CP437_LOW: [char; 32] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+// Caractere Unicode de um byte da página de código 437.
+fn cp437_to_unicode(byte: u8) -> char {
+    match byte {
+        0x00..=0x1f => CP437_LOW[usize::from(byte)],
+        0x7f => '⌂',
+        0x80..=0xff => CP437_HIGH[usize::from(byte - 0x80)],
+        _ => byte as char,
+    }
+}
+
+/* Converte um caractere Unicode para o byte da página de código 437. Os bytes 0x08 a 0x0a e 0x0d
+* não são utilizados, pois o Writer os interpreta como caracteres de controle. Caracteres parecidos
+* com os da página, como as letras sem acento "ã" e "õ", são exibidos de forma aproximada.
+*/
+pub fn unicode_to_cp437(character: char) -> Option<u8> {
+    if matches!(character, ' '..='~') {
+        return Some(character as u8);
+    }
+    if character == '⌂' {
+        return Some(0x7f);
+    }
+    if let Some(index) = CP437_HIGH.iter().position(|&c| c == character) {
+        return Some(0x80 + index as u8);
+    }
+    if let Some(index) = CP437_LOW.iter().skip(1).position(|&c| c == character) {
+        let byte = index as u8 + 1;
+        return if matches!(byte, 0x08..=0x0a | 0x0d) { None } else { Some(byte) };
+    }
+    let byte = match character {
+        'β' => 0xe1, 'μ' => 0xe6, '\u{2126}' => 0xea, '∅' => 0xed, '∈' => 0xee, '∑' => 0xe4,
+        '¦' => b'|', '¨' => b'"', '\u{ad}' => b'-', '´' => b'\'', '¸' => b',', '×' => b'x',
+        '‘' | '’' => b'\'', '“' | '”' => b'"', '‐' | '–' | '—' | '−' => b'-', '≠' => b'#',
+        'À' | 'Á' | 'Â' | 'Ã' => b'A', 'È' | 'Ê' | 'Ë' => b'E', 'Ì' | 'Í' | 'Î' | 'Ï' => b'I',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ø' => b'O', 'Ù' | 'Ú' | 'Û' => b'U', 'Ý' => b'Y',
+        'ã' => b'a', 'õ' | 'ø' => b'o', 'ý' => b'y',
+        _ => return None,
+    };
+    Some(byte)
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
+
+
+// Semelhante a macro print, porém imprime no buffer VGA
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::vga_buffer::_print(format_args!($($arg)*)));
+}
+
+// Semelhante a macro println, porém imprime no buffer VGA
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+// Imprime a string formatada no buffer do VGA através da instancia global WRITER
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    use x86_64::instructions::interrupts;
+
+    /* Desabilitamos as interrupções enquanto o WRITER está bloqueado. Caso contrário, um manipulador
+    * de interrupção que imprime na tela ficaria esperando para sempre pelo lock (deadlock).
+    */
+    interrupts::without_interrupts(|| {
+        match crate::framebuffer::CONSOLE.lock().as_mut() {                                         // O console do framebuffer substitui o modo texto quando habilitado
+            Some(console) => console.write_fmt(args).unwrap(),
+            None => WRITER.lock().write_fmt(args).unwrap(),
+        }
+    });
+}
+
+/* Desenha o ponteiro do mouse em uma célula do console ativo, ou o esconde com None. O caractere
+* original é guardado e restaurado quando o ponteiro sai da célula.
+*/
+pub fn set_pointer(cell: Option<(usize, usize)>) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = CONSOLES[active_console()].lock();
+        match cell {
+            Some((row, col)) => writer.show_pointer(row, col),
+            None => {
+                writer.hide_pointer();
+            }
+        }
+    });
+}
+
+pub fn init() {
+    if let Some(lines) = crate::cmdline::get("scrollback").and_then(|lines| lines.parse().ok()) {
+        set_scrollback_lines(lines);
+    }
+}
+
+// Define quantas linhas o histórico guarda, limitado a SCROLLBACK_CAPACITY. O histórico atual é descartado.
+pub fn set_scrollback_lines(lines: usize) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        for console in CONSOLES.iter() {
+            let mut writer = console.lock();
+            writer.restore_live_view();
+            writer.scrollback.lock().set_limit(lines);
+        }
+    });
+}
+
+// Rola a visualização da tela para trás no histórico, utilizado pelo Shift+PageUp.
+pub fn scroll_back(lines: usize) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| CONSOLES[active_console()].lock().scroll_view_up(lines));
+}
+
+// Rola a visualização da tela em direção à saída atual, utilizado pelo Shift+PageDown.
+pub fn scroll_forward(lines: usize) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| CONSOLES[active_console()].lock().scroll_view_down(lines));
+}
+
+pub fn active_console() -> usize {
+    ACTIVE_CONSOLE.load(Ordering::SeqCst)
+}
+
+/* Exibe o console index, guardando a tela do console atual no seu buffer próprio. Retorna false se o
+* console não existe ou se o VGA está em um modo gráfico.
+*/
+pub fn switch_console(index: usize) -> bool {
+    use x86_64::instructions::interrupts;
+
+    if index >= CONSOLE_COUNT || graphics_mode().is_some() {
+        return false;
+    }
+    interrupts::without_interrupts(|| {
+        let current = active_console();
+        if current == index {
+            return;
+        }
+        let mut first = CONSOLES[current.min(index)].lock();                                        // Os locks são obtidos sempre na ordem dos índices
+        let mut second = CONSOLES[current.max(index)].lock();
+        let (old, new) = if current < index { (&mut *first, &mut *second) } else { (&mut *second, &mut *first) };
+        let pointer = old.hide_pointer();                                                           // O ponteiro continua na mesma célula do novo console
+        let vga = old.deactivate();
+        new.activate(vga);
+        new.restore_pointer(pointer);
+        ACTIVE_CONSOLE.store(index, Ordering::SeqCst);
+    });
+    true
+}
+
+fn copy_buffer(source: &Buffer, destination: &mut Buffer) {
+    for (source_row, destination_row) in source.chars.iter().zip(destination.chars.iter_mut()) {
+        for (source_char, destination_char) in source_row.iter().zip(destination_row.iter_mut()) {
+            destination_char.write(source_char.read());
+        }
+    }
+}
+
+// Imprime um único caractere, utilizado pelo eco do teclado.
+pub fn print_char(character: char) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        match crate::framebuffer::CONSOLE.lock().as_mut() {
+            Some(console) => console.write_char(character),
+            None => WRITER.lock().write_char(character),
+        }
+    });
+}
+
+/* As funções abaixo consultam o console do framebuffer quando ele está habilitado e o Writer do modo
+* texto caso contrário, assim o shell não precisa saber qual dos dois exibe a saída.
+*/
+pub fn column_position() -> usize {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        match crate::framebuffer::CONSOLE.lock().as_ref() {
+            Some(console) => console.column_position(),
+            None => WRITER.lock().column_position(),
+        }
+    })
+}
+
+// Quantidade de colunas de texto da tela.
+pub fn screen_width() -> usize {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        crate::framebuffer::CONSOLE.lock().as_ref().map_or(BUFFER_WIDTH, |console| console.columns())
+    })
+}
+
+pub fn clear_screen() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        match crate::framebuffer::CONSOLE.lock().as_mut() {
+            Some(console) => console.clear_screen(),
+            None => WRITER.lock().clear_screen(),
+        }
+    });
+}
+
+/* Modos gráficos do VGA programados diretamente nos registradores, sem utilizar a BIOS. Um modo é
+* definido pelos valores do registrador Miscellaneous Output, do Sequencer, do CRT Controller, do
+* Graphics Controller e do Attribute Controller. Ao sair do modo texto, a fonte (que fica no plano 2
+* da memória do VGA e é apagada pelo desenho) e a paleta do DAC são guardadas, e o console ativo passa
+* a escrever apenas no seu buffer próprio até a volta para o modo texto.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GraphicsMode {
+    Mode13h = 1,                                                                                    // 320x200 com 256 cores, um byte por pixel
+    Mode12h = 2,                                                                                    // 640x480 com 16 cores, um bit por pixel em cada um dos quatro planos
+}
+
+impl GraphicsMode {
+    pub fn width(self) -> usize {
+        match self {
+            GraphicsMode::Mode13h => 320,
+            GraphicsMode::Mode12h => 640,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            GraphicsMode::Mode13h => 200,
+            GraphicsMode::Mode12h => 480,
+        }
+    }
+
+    pub fn colors(self) -> usize {
+        match self {
+            GraphicsMode::Mode13h => 256,
+            GraphicsMode::Mode12h => 16,
+        }
+    }
+
+    fn registers(self) -> &'static VgaRegisters {
+        match self {
+            GraphicsMode::Mode13h => &MODE_13H_REGISTERS,
+            GraphicsMode::Mode12h => &MODE_12H_REGISTERS,
+        }
+    }
+}
+
+struct VgaRegisters {
+    misc: u8,
+    sequencer: [u8; 5],
+    crtc: [u8; 25],
+    graphics: [u8; 9],
+    attribute: [u8; 21],
+}
+
+const TEXT_MODE_REGISTERS: VgaRegisters = VgaRegisters {
+    misc: 0x67,
+    sequencer: [0x03, 0x00, 0x03, 0x00, 0x02],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x55, 0x81, 0xbf, 0x1f, 0x00, 0x4f, 0x0d, 0x0e, 0x00, 0x00, 0x00, 0x50,
+        0x9c, 0x0e, 0x8f, 0x28, 0x1f, 0x96, 0xb9, 0xa3, 0xff,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x0e, 0x00, 0xff],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x14, 0x07, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+        0x0c, 0x00, 0x0f, 0x08, 0x00,
+    ],
+};
+
+const MODE_13H_REGISTERS: VgaRegisters = VgaRegisters {
+    misc: 0x63,
+    sequencer: [0x03, 0x01, 0x0f, 0x00, 0x0e],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x54, 0x80, 0xbf, 0x1f, 0x00, 0x41, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x9c, 0x0e, 0x8f, 0x28, 0x40, 0x96, 0xb9, 0xa3, 0xff,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x05, 0x0f, 0xff],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x41, 0x00, 0x0f, 0x00, 0x00,
+    ],
+};
+
+const MODE_12H_REGISTERS: VgaRegisters = VgaRegisters {
+    misc: 0xe3,
+    sequencer: [0x03, 0x01, 0x08, 0x00, 0x06],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x54, 0x80, 0x0b, 0x3e, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xea, 0x0c, 0xdf, 0x28, 0x00, 0xe7, 0x04, 0xe3, 0xff,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x05, 0x0f, 0xff],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x14, 0x07, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+        0x01, 0x00, 0x0f, 0x00, 0x00,
+    ],
+};
+
+const MISC_WRITE_PORT: u16 = 0x3c2;
+const SEQUENCER_INDEX_PORT: u16 = 0x3c4;
+const SEQUENCER_DATA_PORT: u16 = 0x3c5;
+const GRAPHICS_INDEX_PORT: u16 = 0x3ce;
+const GRAPHICS_DATA_PORT: u16 = 0x3cf;
+const ATTRIBUTE_PORT: u16 = 0x3c0;                                                                  // Recebe o índice e o valor alternadamente
+const INPUT_STATUS_PORT: u16 = 0x3da;                                                               // Sua leitura faz a porta 0x3c0 voltar a esperar um índice
+const DAC_READ_INDEX_PORT: u16 = 0x3c7;
+const DAC_WRITE_INDEX_PORT: u16 = 0x3c8;
+const DAC_DATA_PORT: u16 = 0x3c9;
+
+const SEQUENCER_MAP_MASK: u8 = 0x02;
+const SEQUENCER_MEMORY_MODE: u8 = 0x04;
+const GRAPHICS_READ_MAP: u8 = 0x04;
+const GRAPHICS_MODE: u8 = 0x05;
+const GRAPHICS_MISC: u8 = 0x06;
+const GRAPHICS_BIT_MASK: u8 = 0x08;
+const GRAPHICS_WRITE_MODE_2: u8 = 0x02;                                                             // O byte escrito é a cor, aplicada aos bits liberados pela máscara
+const CRTC_VERTICAL_RETRACE_END: u8 = 0x11;
+const CRTC_PROTECT: u8 = 1 << 7;                                                                    // Bloqueia a escrita nos registradores 0 a 7 do CRTC
+const ATTRIBUTE_PALETTE_ENABLE: u8 = 1 << 5;
+
+const GRAPHICS_MEMORY: u64 = 0xa0000;
+const FONT_PLANE_SIZE: usize = 256 * 32;                                                            // 256 glifos, cada um ocupando 32 bytes no plano 2
+const PALETTE_SIZE: usize = 256 * 3;
+const TEXT_MODE: u8 = 0;
+
+static VIDEO_MODE: AtomicU8 = AtomicU8::new(TEXT_MODE);
+static SAVED_TEXT_MODE: Mutex<TextModeState> = Mutex::new(TextModeState::new());
+
+struct TextModeState {
+    font: [u8; FONT_PLANE_SIZE],
+    palette: [u8; PALETTE_SIZE],
+}
+
+impl TextModeState {
+    const fn new() -> TextModeState {
+        TextModeState { font: [0; FONT_PLANE_SIZE], palette: [0; PALETTE_SIZE] }
+    }
+}
+
+fn sequencer_write(index: u8, value: u8) {
+    unsafe {
+        Port::new(SEQUENCER_INDEX_PORT).write(index);
+        Port::new(SEQUENCER_DATA_PORT).write(value);
+    }
+}
+
+fn graphics_write(index: u8, value: u8) {
+    unsafe {
+        Port::new(GRAPHICS_INDEX_PORT).write(index);
+        Port::new(GRAPHICS_DATA_PORT).write(value);
+    }
+}
+
+fn attribute_write(index: u8, value: u8) {
+    unsafe {
+        Port::<u8>::new(INPUT_STATUS_PORT).read();
+        Port::new(ATTRIBUTE_PORT).write(index);
+        Port::new(ATTRIBUTE_PORT).write(value);
+    }
+}
+
+fn write_registers(registers: &VgaRegisters) {
+    unsafe { Port::new(MISC_WRITE_PORT).write(registers.misc) };
+    for (index, &value) in registers.sequencer.iter().enumerate() {
+        sequencer_write(index as u8, value);
+    }
+    crtc_write(CRTC_VERTICAL_RETRACE_END, crtc_read(CRTC_VERTICAL_RETRACE_END) & !CRTC_PROTECT);
+    for (index, &value) in registers.crtc.iter().enumerate() {
+        let value = if index as u8 == CRTC_VERTICAL_RETRACE_END { value & !CRTC_PROTECT } else { value };
+        crtc_write(index as u8, value);
+    }
+    for (index, &value) in registers.graphics.iter().enumerate() {
+        graphics_write(index as u8, value);
+    }
+    for (index, &value) in registers.attribute.iter().enumerate() {
+        attribute_write(index as u8, value);
+    }
+    /* Enquanto o bit 5 do índice estiver desligado, a paleta fica sob controle da CPU e a tela fica
+    * apagada, então ele é ligado depois de programar o Attribute Controller.
+    */
+    attribute_write(ATTRIBUTE_PALETTE_ENABLE, 0);
+}
+
+fn graphics_memory() -> *mut u8 {
+    crate::memory::phys_to_virt(x86_64::PhysAddr::new(GRAPHICS_MEMORY)).as_mut_ptr()
+}
+
+/* Torna o plano 2, onde ficam as fontes do modo texto, acessível em 0xa0000 com endereçamento
+* sequencial. O modo texto utiliza endereçamento par/ímpar, que esconde esse plano da CPU.
+*/
+fn map_font_plane() {
+    sequencer_write(SEQUENCER_MAP_MASK, 1 << 2);
+    sequencer_write(SEQUENCER_MEMORY_MODE, 0x07);
+    graphics_write(GRAPHICS_READ_MAP, 2);
+    graphics_write(GRAPHICS_MODE, 0x00);
+    graphics_write(GRAPHICS_MISC, 0x04);
+}
+
+fn unmap_font_plane() {
+    let registers = &TEXT_MODE_REGISTERS;
+    sequencer_write(SEQUENCER_MAP_MASK, registers.sequencer[SEQUENCER_MAP_MASK as usize]);
+    sequencer_write(SEQUENCER_MEMORY_MODE, registers.sequencer[SEQUENCER_MEMORY_MODE as usize]);
+    graphics_write(GRAPHICS_READ_MAP, registers.graphics[GRAPHICS_READ_MAP as usize]);
+    graphics_write(GRAPHICS_MODE, registers.graphics[GRAPHICS_MODE as usize]);
+    graphics_write(GRAPHICS_MISC, registers.graphics[GRAPHICS_MISC as usize]);
+}
+
+fn read_font_plane(font: &mut [u8; FONT_PLANE_SIZE]) {
+    map_font_plane();
+    let memory = graphics_memory();
+    for (offset, byte) in font.iter_mut().enumerate() {
+        *byte = unsafe { memory.add(offset).read_volatile() };
+    }
+    unmap_font_plane();
+}
+
+fn write_font_plane(font: &[u8; FONT_PLANE_SIZE]) {
+    map_font_plane();
+    let memory = graphics_memory();
+    for (offset, &byte) in font.iter().enumerate() {
+        unsafe { memory.add(offset).write_volatile(byte) };
+    }
+    unmap_font_plane();
+}
+
+// Guarda a fonte e a paleta em uso no modo texto.
+fn save_text_mode(state: &mut TextModeState) {
+    read_font_plane(&mut state.font);
+    read_palette(&mut state.palette);
+}
+
+fn restore_text_mode(state: &TextModeState) {
+    write_font_plane(&state.font);
+    write_palette(&state.palette);
+}
+
+// O DAC guarda as cores com 6 bits por componente, lidas e escritas em sequência a partir de um índice.
+fn read_palette(palette: &mut [u8; PALETTE_SIZE]) {
+    unsafe {
+        Port::new(DAC_READ_INDEX_PORT).write(0u8);
+        for component in palette.iter_mut() {
+            *component = Port::new(DAC_DATA_PORT).read();
+        }
+    }
+}
+
+fn write_palette(palette: &[u8; PALETTE_SIZE]) {
+    unsafe {
+        Port::new(DAC_WRITE_INDEX_PORT).write(0u8);
+        for &component in palette.iter() {
+            Port::new(DAC_DATA_PORT).write(component);
+        }
+    }
+}
+
+// Define uma cor da paleta do DAC a partir de 0xRRGGBB. Os 2 bits menos significativos de cada componente são descartados.
+pub fn set_palette_color(index: u8, color: u32) {
+    unsafe {
+        Port::new(DAC_WRITE_INDEX_PORT).write(index);
+        for component in dac_components(color) {
+            Port::new(DAC_DATA_PORT).write(component);
+        }
+    }
+}
+
+pub fn palette_color(index: u8) -> u32 {
+    let mut components = [0; 3];
+    unsafe {
+        Port::new(DAC_READ_INDEX_PORT).write(index);
+        for component in components.iter_mut() {
+            *component = Port::new(DAC_DATA_PORT).read();
+        }
+    }
+    dac_color(&components)
+}
+
+fn dac_components(color: u32) -> [u8; 3] {
+    [(color >> 16) as u8 >> 2, (color >> 8) as u8 >> 2, color as u8 >> 2]
+}
+
+// Converte os componentes de 6 bits para 8 bits, repetindo os bits mais significativos nos menos significativos.
+fn dac_color(components: &[u8]) -> u32 {
+    components.iter().fold(0, |color, &component| color << 8 | u32::from(component << 2 | component >> 4))
+}
+
+/* Paleta de 256 cores do modo 13h: as 16 cores do modo texto, um cubo de 6x6x6 cores e 24 tons de
+* cinza, na mesma disposição da paleta de 256 cores dos terminais.
+*/
+pub fn default_palette_color(index: u8) -> u32 {
+    const LEVELS: [u32; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+    match index {
+        0..=15 => crate::framebuffer::PALETTE[index as usize],
+        16..=231 => {
+            let cube = u32::from(index - 16);
+            LEVELS[(cube / 36) as usize] << 16 | LEVELS[(cube / 6 % 6) as usize] << 8 | LEVELS[(cube % 6) as usize]
+        }
+        _ => {
+            let level = 8 + 10 * u32::from(index - 232);
+            level << 16 | level << 8 | level
+        }
+    }
+}
+
+pub fn graphics_mode() -> Option<GraphicsMode> {
+    match VIDEO_MODE.load(Ordering::SeqCst) {
+        1 => Some(GraphicsMode::Mode13h),
+        2 => Some(GraphicsMode::Mode12h),
+        _ => None,
+    }
+}
+
+/* Muda o VGA para um modo gráfico e retorna a tela para desenho, já limpa. Retorna None quando o
+* console do framebuffer está em uso, pois a imagem exibida é a do adaptador BGA.
+*/
+pub fn set_graphics_mode(mode: GraphicsMode) -> Option<VgaGraphics> {
+    use x86_64::instructions::interrupts;
+
+    if crate::framebuffer::is_enabled() {
+        return None;
+    }
+    interrupts::without_interrupts(|| {
+        let mut writer = CONSOLES[active_console()].lock();
+        if graphics_mode().is_none() {
+            let mut state = SAVED_TEXT_MODE.lock();
+            save_text_mode(&mut state);
+            writer.deactivate();
+        }
+        write_registers(mode.registers());
+        match mode {
+            GraphicsMode::Mode13h => (0..=255).for_each(|index| set_palette_color(index, default_palette_color(index))),
+            GraphicsMode::Mode12h => write_palette(&SAVED_TEXT_MODE.lock().palette),                // Utiliza as mesmas 16 entradas do DAC que o modo texto
+        }
+        VIDEO_MODE.store(mode as u8, Ordering::SeqCst);
+    });
+    let mut screen = VgaGraphics { mode };
+    screen.clear(0);
+    Some(screen)
+}
+
+// Volta para o modo texto de 80x25, restaurando a fonte, a paleta e o conteúdo do console ativo.
+pub fn set_text_mode() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        if graphics_mode().is_none() {
+            return;
+        }
+        let mut writer = CONSOLES[active_console()].lock();
+        write_registers(&TEXT_MODE_REGISTERS);
+        restore_text_mode(&SAVED_TEXT_MODE.lock());
+        VIDEO_MODE.store(TEXT_MODE, Ordering::SeqCst);
+        set_line_graphics(custom_font().is_none());
+        writer.activate(unsafe { &mut *(0xb8000 as *mut Buffer) });
+    });
+}
+
+/* Tela do modo gráfico, desenhada com as primitivas do módulo graphics. A cor é o índice na paleta.
+* Depois da volta ao modo texto, os desenhos são ignorados.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct VgaGraphics {
+    mode: GraphicsMode,
+}
+
+impl VgaGraphics {
+    pub fn mode(&self) -> GraphicsMode {
+        self.mode
+    }
+
+    fn is_current(&self) -> bool {
+        VIDEO_MODE.load(Ordering::SeqCst) == self.mode as u8
+    }
+
+    pub fn clear(&mut self, color: u8) {
+        if !self.is_current() {
+            return;
+        }
+        let memory = graphics_memory();
+        match self.mode {
+            GraphicsMode::Mode13h => unsafe { core::ptr::write_bytes(memory, color, 320 * 200) },
+            GraphicsMode::Mode12h => {
+                graphics_write(GRAPHICS_MODE, GRAPHICS_WRITE_MODE_2);
+                for offset in 0..640 * 480 / 8 {
+                    unsafe { memory.add(offset).write_volatile(color) };
+                }
+                graphics_write(GRAPHICS_MODE, MODE_12H_REGISTERS.graphics[GRAPHICS_MODE as usize]);
+            }
+        }
+    }
+}
+
+impl crate::graphics::Canvas for VgaGraphics {
+    fn width(&self) -> usize {
+        self.mode.width()
+    }
+
+    fn height(&self) -> usize {
+        self.mode.height()
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if !self.is_current() || x >= self.mode.width() || y >= self.mode.height() {
+            return;
+        }
+        let memory = graphics_memory();
+        match self.mode {
+            GraphicsMode::Mode13h => unsafe { memory.add(y * 320 + x).write_volatile(color as u8) },
+            GraphicsMode::Mode12h => {
+                /* No modo de escrita 2 apenas o bit do pixel é liberado pela máscara, e os demais bits
+                * do byte vêm dos latches, carregados pela leitura anterior à escrita.
+                */
+                graphics_write(GRAPHICS_MODE, GRAPHICS_WRITE_MODE_2);
+                graphics_write(GRAPHICS_BIT_MASK, 0x80 >> (x % 8));
+                unsafe {
+                    let address = memory.add(y * 80 + x / 8);
+                    address.read_volatile();
+                    address.write_volatile(color as u8 & 0x0f);
+                }
+                graphics_write(GRAPHICS_BIT_MASK, 0xff);
+                graphics_write(GRAPHICS_MODE, MODE_12H_REGISTERS.graphics[GRAPHICS_MODE as usize]);
+            }
+        }
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        if !self.is_current() || x >= self.mode.width() || y >= self.mode.height() {
+            return 0;
+        }
+        let memory = graphics_memory();
+        match self.mode {
+            GraphicsMode::Mode13h => u32::from(unsafe { memory.add(y * 320 + x).read_volatile() }),
+            GraphicsMode::Mode12h => {
+                let color = (0..4).fold(0, |color, plane| {
+                    graphics_write(GRAPHICS_READ_MAP, plane);
+                    let byte = unsafe { memory.add(y * 80 + x / 8).read_volatile() };
+                    color | u32::from(byte >> (7 - x % 8) & 1) << plane
+                });
+                graphics_write(GRAPHICS_READ_MAP, MODE_12H_REGISTERS.graphics[GRAPHICS_READ_MAP as usize]);
+                color
+            }
+        }
+    }
+}
+
+/* Fontes e cores do modo texto. Uma fonte carregada por load_font substitui os 256 glifos do plano 2 e
+* passa a ser utilizada pelo Writer para converter os caracteres em bytes, através da sua tabela
+* Unicode. A fonte e a paleta originais da BIOS são guardadas na primeira alteração, para que possam
+* ser restauradas depois.
+*/
+pub const TEXT_FONT_WIDTH: usize = 8;
+pub const TEXT_FONT_HEIGHT: usize = 16;
+const GLYPH_SLOT_SIZE: usize = 32;
+const ATTRIBUTE_MODE_CONTROL: u8 = 0x10;
+const LINE_GRAPHICS: u8 = 1 << 2;                                                                   // Repete a 8ª coluna dos glifos 0xc0 a 0xdf na 9ª coluna da célula
+
+static TEXT_FONT: AtomicPtr<Font> = AtomicPtr::new(core::ptr::null_mut());
+static BIOS_TEXT_MODE: Mutex<TextModeState> = Mutex::new(TextModeState::new());
+static BIOS_TEXT_MODE_SAVED: AtomicBool = AtomicBool::new(false);
+
+fn custom_font() -> Option<&'static Font> {
+    unsafe { TEXT_FONT.load(Ordering::SeqCst).as_ref() }
+}
+
+// Byte exibido para um caractere, de acordo com a fonte em uso.
+fn text_glyph(character: char) -> u8 {
+    font_glyph(custom_font(), character)
+}
+
+// Byte de um caractere na fonte informada, ou na página de código 437 com None.
+fn font_glyph(font: Option<&Font>, character: char) -> u8 {
+    match font {
+        Some(font) => {
+            let glyph = font.glyph_index(character).filter(|&glyph| glyph < 256);
+            glyph.unwrap_or_else(|| font.fallback_glyph()).min(255) as u8
+        }
+        None => unicode_to_cp437(character).unwrap_or(FALLBACK_GLYPH),                              // Um único glifo para cada caractere sem representação
+    }
+}
+
+/* Tabela que converte os bytes gravados com a fonte from nos bytes do mesmo caractere na fonte to.
+* O byte 0, das linhas vazias do histórico, e o espaço, gravado diretamente ao apagar a tela, são
+* mantidos.
+*/
+fn glyph_translation(from: Option<&Font>, to: Option<&Font>) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (byte, slot) in table.iter_mut().enumerate() {
+        let character = match from {
+            Some(font) => font.glyph_character(byte),
+            None => Some(cp437_to_unicode(byte as u8)),
+        };
+        *slot = match character {
+            Some(character) if byte != 0 && byte != usize::from(b' ') => font_glyph(to, character),
+            _ => byte as u8,
+        };
+    }
+    table
+}
+
+/* Os bytes da tela, dos buffers próprios dos consoles e do histórico foram gravados com a fonte
+* anterior, então são convertidos para a nova fonte para continuarem exibindo os mesmos caracteres.
+*/
+fn translate_consoles(table: &[u8; 256]) {
+    for console in CONSOLES.iter() {
+        console.lock().translate_glyphs(table);
+    }
+}
+
+// Guarda a fonte e a paleta da BIOS antes da primeira alteração.
+fn save_bios_text_mode() {
+    if BIOS_TEXT_MODE_SAVED.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut bios = BIOS_TEXT_MODE.lock();
+    match graphics_mode() {
+        None => save_text_mode(&mut bios),
+        Some(_) => {
+            let saved = SAVED_TEXT_MODE.lock();
+            bios.font = saved.font;
+            bios.palette = saved.palette;
+        }
+    }
+    BIOS_TEXT_MODE_SAVED.store(true, Ordering::SeqCst);
+}
+
+// Em um modo gráfico, a fonte é guardada para ser carregada na volta ao modo texto.
+fn set_text_font(font: &[u8; FONT_PLANE_SIZE]) {
+    match graphics_mode() {
+        None => write_font_plane(font),
+        Some(_) => SAVED_TEXT_MODE.lock().font = *font,
+    }
+}
+
+/* Os caracteres de desenho de caixas da página 437 ocupam os bytes 0xc0 a 0xdf, e o VGA estende as
+* suas linhas até a coluna de espaçamento. Nas demais fontes esses bytes são letras, então a extensão
+* é desligada.
+*/
+fn set_line_graphics(enabled: bool) {
+    if graphics_mode().is_some() {
+        return;
+    }
+    let mode_control = TEXT_MODE_REGISTERS.attribute[ATTRIBUTE_MODE_CONTROL as usize] & !LINE_GRAPHICS;
+    attribute_write(ATTRIBUTE_MODE_CONTROL | ATTRIBUTE_PALETTE_ENABLE, mode_control | if enabled { LINE_GRAPHICS } else { 0 });
+}
+
+/* Carrega os 256 primeiros glifos de uma fonte de 8x16 no plano 2. Retorna false se a fonte tiver
+* outro tamanho, pois as células do modo texto têm 16 linhas.
+*/
+pub fn load_font(font: &'static Font) -> bool {
+    use x86_64::instructions::interrupts;
+
+    if font.width() != TEXT_FONT_WIDTH || font.height() != TEXT_FONT_HEIGHT {
+        return false;
+    }
+    let mut glyphs = [0; FONT_PLANE_SIZE];
+    for (index, slot) in glyphs.chunks_exact_mut(GLYPH_SLOT_SIZE).take(font.glyph_count()).enumerate() {
+        slot[..TEXT_FONT_HEIGHT].copy_from_slice(&font.glyph(index)[..TEXT_FONT_HEIGHT]);
+    }
+    interrupts::without_interrupts(|| {
+        save_bios_text_mode();
+        set_text_font(&glyphs);
+        let table = glyph_translation(custom_font(), Some(font));
+        TEXT_FONT.store(font as *const Font as *mut Font, Ordering::SeqCst);
+        translate_consoles(&table);
+        set_line_graphics(false);
+    });
+    true
+}
+
+// Volta para a fonte da BIOS e para a conversão pela página de código 437.
+pub fn restore_default_font() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        if BIOS_TEXT_MODE_SAVED.load(Ordering::SeqCst) {
+            set_text_font(&BIOS_TEXT_MODE.lock().font);
+        }
+        let table = glyph_translation(custom_font(), None);
+        TEXT_FONT.store(core::ptr::null_mut(), Ordering::SeqCst);
+        translate_consoles(&table);
+        set_line_graphics(true);
+    });
+}
+
+// Entrada do DAC utilizada por uma cor, definida pelo registrador de paleta do Attribute Controller.
+fn color_dac_index(color: Color) -> usize {
+    TEXT_MODE_REGISTERS.attribute[color as usize] as usize
+}
+
+// Define o valor RGB (0xRRGGBB) exibido para uma cor do modo texto.
+pub fn set_color_rgb(color: Color, rgb: u32) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        save_bios_text_mode();
+        let index = color_dac_index(color);
+        match graphics_mode() {
+            None => set_palette_color(index as u8, rgb),
+            Some(_) => SAVED_TEXT_MODE.lock().palette[index * 3..index * 3 + 3].copy_from_slice(&dac_components(rgb)),
+        }
+    });
+}
+
+pub fn color_rgb(color: Color) -> u32 {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let index = color_dac_index(color);
+        match graphics_mode() {
+            None => palette_color(index as u8),
+            Some(_) => dac_color(&SAVED_TEXT_MODE.lock().palette[index * 3..index * 3 + 3]),
+        }
+    })
+}
+
+pub fn restore_default_palette() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        if !BIOS_TEXT_MODE_SAVED.load(Ordering::SeqCst) {
+            return;
+        }
+        let bios = BIOS_TEXT_MODE.lock();
+        match graphics_mode() {
+            None => write_palette(&bios.palette),
+            Some(_) => SAVED_TEXT_MODE.lock().palette = bios.palette,
+        }
+    });
+}
+
+#[test_case]
+fn test_println_simple() {
+    println!("test_println_simple output");
+}
+
+#[test_case]
+fn test_println_many() {
+    for _ in 0..200 {
+        println!("test_println_many output");
+    }
+}
+
+#[test_case]
+fn test_println_output() {
+    /* A função define uma string e itera sobre ela e os caracteres impressos no buffer e compara.
+     * Como o println coloca uma linha nova, a string deve estar na linha anterior.
+    */
+    let s = "Some test string that fits on a single line";
+    println!("{}", s);
+    for (i, c) in s.chars().enumerate() {
+        let screen_char = WRITER.lock().buffer.chars[BUFFER_HEIGHT - 2][i].read();
+        assert_eq!(char::from(screen_char.ascii_character), c);
+    }
+}
+
+
+
+
+
+
+
+#[test_case]
+fn test_write_char_latin1() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.write_string("\n");
+        for character in ['ç', 'é', 'ã', '°'] {
+            writer.write_char(character);
+        }
+        let row = &writer.buffer.chars[BUFFER_HEIGHT - 1];
+        let glyphs = [row[0].read(), row[1].read(), row[2].read(), row[3].read()].map(|c| c.ascii_character);
+        assert_eq!(glyphs, [0x87, 0x82, b'a', 0xf8]);
+    });
+}
+
+#[test_case]
+fn test_write_string_utf8() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.write_string("\n╔═╗ αβ€ñ");
+        let row = &writer.buffer.chars[BUFFER_HEIGHT - 1];
+        let mut glyphs = [0; 8];
+        for (glyph, cell) in glyphs.iter_mut().zip(row.iter()) {
+            *glyph = cell.read().ascii_character;
+        }
+        assert_eq!(glyphs, [0xc9, 0xcd, 0xbb, b' ', 0xe0, 0xe1, FALLBACK_GLYPH, 0xa4]);
+        assert_eq!(writer.column_position(), 8);
+    });
+}
+
+#[test_case]
+fn test_cursor_follows_writer() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.write_string("\nabc");
+        let position = u16::from(crtc_read(CRTC_CURSOR_LOCATION_HIGH)) << 8 | u16::from(crtc_read(CRTC_CURSOR_LOCATION_LOW));
+        assert_eq!(usize::from(position), (BUFFER_HEIGHT - 1) * BUFFER_WIDTH + 3);
+
+        writer.set_cursor_shape(CursorShape::Block);
+        assert_eq!(crtc_read(CRTC_CURSOR_START) & CURSOR_SCANLINE_MASK, 0);
+        writer.set_cursor_shape(CursorShape::Underline);
+        writer.write_string("\n");
+    });
+}
+
+#[test_case]
+fn test_write_at_keeps_position_and_color() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        let position = writer.position();
+        let color_code = writer.color_code;
+        writer.write_at_colored(3, 10, "xy", Color::White, Color::Blue);
+        assert_eq!(writer.position(), position);
+        assert_eq!(writer.color_code, color_code);
+
+        let cell = writer.buffer.chars[3][11].read();
+        assert_eq!(cell.ascii_character, b'y');
+        assert_eq!(cell.color_code, ColorCode::new(Color::White, Color::Blue));
+    });
+}
+
+#[test_case]
+fn test_control_characters() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.set_position(5, 0);
+        writer.write_string("ab\tc");
+        assert_eq!(writer.position(), (5, TAB_WIDTH + 1));
+        writer.write_string("\rd\x08");
+        assert_eq!(writer.position(), (5, 0));
+        assert_eq!(writer.buffer.chars[5][0].read().ascii_character, b'd');
+
+        writer.set_position(5, 1);
+        writer.clear_to_end_of_line();
+        assert_eq!(writer.buffer.chars[5][TAB_WIDTH].read().ascii_character, b' ');
+        writer.write_string("\n");
+        assert_eq!(writer.position(), (6, 0));
+        writer.set_position(BUFFER_HEIGHT - 1, 0);
+    });
+}
+
+#[test_case]
+fn test_escape_parser() {
+    let mut parser = EscapeParser::new();
+    let mut feed = |bytes: &[u8]| bytes.iter().map(|&byte| parser.advance(byte)).last().unwrap();
+
+    match feed(b"\x1b[12;5H") {
+        EscapeAction::Csi(sequence) => {
+            assert_eq!((sequence.command, sequence.private), (b'H', false));
+            assert_eq!(sequence.params(), &[12, 5]);
+            assert_eq!(sequence.param(2, 1), 1);
+        }
+        action => panic!("acao inesperada: {:?}", action),
+    }
+    match feed(b"\x1b[?25l") {
+        EscapeAction::Csi(sequence) => assert!(sequence.private && sequence.param(0, 0) == 25),
+        action => panic!("acao inesperada: {:?}", action),
+    }
+    assert_eq!(feed(b"\x1b7"), EscapeAction::Escape(b'7'));
+    assert_eq!(feed(b"\x1b[\x18"), EscapeAction::None);
+    assert!(!parser.in_sequence());
+}
+
+#[test_case]
+fn test_ansi_colors_and_cursor() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        let color_code = writer.color_code;
+        writer.write_string("\x1b[2;3H\x1b[1;31mA\x1b[0m\x1b[44mB\x1b[m");
+        assert_eq!(writer.position(), (1, 4));
+        assert_eq!(writer.color_code, color_code);
+
+        let a = writer.buffer.chars[1][2].read();
+        let b = writer.buffer.chars[1][3].read();
+        assert_eq!((a.ascii_character, a.color_code), (b'A', ColorCode::new(Color::LightRed, Color::Black)));
+        assert_eq!((b.ascii_character, b.color_code), (b'B', ColorCode::new(Color::Yellow, Color::Blue)));
+
+        writer.write_string("\x1b[3D\x1b[K\x1b[s\x1b[5B\x1b[u");
+        assert_eq!(writer.position(), (1, 1));
+        assert_eq!(writer.buffer.chars[1][2].read().ascii_character, b' ');
+        writer.write_string("\x1b[25;1H");
+    });
+}
+
+#[test_case]
+fn test_ansi_scroll_region() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.write_at(9, 0, "x");
+        writer.write_string("\x1b[11;13r");
+        assert_eq!(writer.position(), (0, 0));
+        writer.write_string("\x1b[13;1Ha\nb");
+        assert_eq!(writer.position(), (12, 1));
+        assert_eq!(writer.buffer.chars[11][0].read().ascii_character, b'a');
+        assert_eq!(writer.buffer.chars[12][0].read().ascii_character, b'b');
+        assert_eq!(writer.buffer.chars[9][0].read().ascii_character, b'x');
+
+        writer.write_string("\x1b[r");
+        assert_eq!((writer.scroll_top, writer.scroll_bottom), (0, BUFFER_HEIGHT - 1));
+
+        writer.write_string("\x1b[99S");                                                            // Mais linhas que a região apenas a apaga
+        assert_eq!(writer.buffer.chars[9][0].read().ascii_character, b' ');
+        assert!(writer.set_region(Region::new(1, BUFFER_HEIGHT - 1)));
+        writer.write_string("\x1b[99999999999999999999;5H");
+        assert_eq!(writer.position(), (BUFFER_HEIGHT - 1, 4));
+        writer.write_string("\x1b[2;99999999999999999999r");
+        assert_eq!((writer.scroll_top, writer.scroll_bottom), (1, BUFFER_HEIGHT - 1));
+        writer.set_region(Region::FULL_SCREEN);
+        writer.set_position(BUFFER_HEIGHT - 1, 0);
+    });
+}
+
+#[test_case]
+fn test_scrollback() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.set_position(BUFFER_HEIGHT - 1, 0);
+        writer.write_string("\nabc\n");
+        for _ in 0..BUFFER_HEIGHT - 1 {
+            writer.write_string("\n");
+        }
+        let live = writer.read_row(0);
+
+        writer.scroll_view_up(1);
+        assert!(writer.is_scrolled_back());
+        let row = writer.read_row(0);
+        assert_eq!([row[0], row[1], row[2]].map(|c| c.ascii_character), *b"abc");
+        assert_eq!(writer.read_row(1), live);
+
+        writer.write_string("");                                                                    // Uma nova escrita volta para a visão ao vivo
+        assert!(!writer.is_scrolled_back());
+        assert_eq!(writer.read_row(0), live);
+    });
+}
+
+#[test_case]
+fn test_virtual_consoles() {
+    use x86_64::instructions::interrupts;
+
+    let bottom_row = || interrupts::without_interrupts(|| WRITER.lock().read_row(BUFFER_HEIGHT - 1));
+    let live = bottom_row();
+    interrupts::without_interrupts(|| {
+        let mut console = CONSOLES[1].lock();
+        assert!(!console.is_active());
+        console.write_string("\nconsole 2");
+    });
+    assert_eq!(bottom_row(), live);
+
+    assert!(switch_console(1));
+    assert_eq!(active_console(), 1);
+    let vga = unsafe { &*(0xb8000 as *const Buffer) };
+    assert_eq!(vga.chars[BUFFER_HEIGHT - 1][8].read().ascii_character, b'2');
+
+    assert!(switch_console(0));
+    assert!(!switch_console(CONSOLE_COUNT));
+    assert_eq!(bottom_row(), live);
+    assert!(interrupts::without_interrupts(|| WRITER.lock().is_active()));
+}
+
+#[test_case]
+fn test_backend_neutral_console() {
+    use x86_64::instructions::interrupts;
+
+    if crate::framebuffer::is_enabled() {
+        return;
+    }
+    assert_eq!(screen_width(), BUFFER_WIDTH);
+    print!("\nabc");
+    assert_eq!(column_position(), interrupts::without_interrupts(|| WRITER.lock().column_position()));
+    assert_eq!(column_position(), 3);
+    println!();
+}
+
+#[test_case]
+fn test_pointer_survives_output() {
+    use x86_64::instructions::interrupts;
+
+    let row = BUFFER_HEIGHT - 2;
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        let color_code = writer.color_code;
+        writer.write_string("\nab\n");
+        writer.show_pointer(row, 0);
+        assert_eq!(writer.buffer.chars[row][0].read(), ScreenChar { ascii_character: b'a', color_code: color_code.inverted() });
+
+        writer.write_string("c\n");                                                                 // A rolagem leva "ab" para cima e "c" para baixo do ponteiro
+        assert_eq!(writer.buffer.chars[row - 1][0].read(), ScreenChar { ascii_character: b'a', color_code });
+        assert_eq!(writer.buffer.chars[row][0].read(), ScreenChar { ascii_character: b'c', color_code: color_code.inverted() });
+
+        writer.clear_screen();
+        assert_eq!(writer.buffer.chars[row][0].read().color_code, color_code.inverted());
+        assert_eq!(writer.hide_pointer(), Some((row, 0)));
+        assert_eq!(writer.buffer.chars[row][0].read(), ScreenChar { ascii_character: b' ', color_code });
+    });
+}
+
+#[test_case]
+fn test_graphics_modes() {
+    use crate::graphics::Canvas;
+    use x86_64::instructions::interrupts;
+
+    let mut before = TextModeState::new();
+    interrupts::without_interrupts(|| save_text_mode(&mut before));
+    let row = interrupts::without_interrupts(|| WRITER.lock().read_row(BUFFER_HEIGHT - 1));
+
+    let mut screen = set_graphics_mode(GraphicsMode::Mode13h).unwrap();
+    assert_eq!(graphics_mode(), Some(GraphicsMode::Mode13h));
+    screen.set_pixel(319, 199, 200);
+    assert_eq!((screen.get_pixel(319, 199), screen.get_pixel(0, 0)), (200, 0));
+    assert_eq!(palette_color(200) >> 16, 0xff);
+    assert!(!switch_console(1));
+
+    let mut screen = set_graphics_mode(GraphicsMode::Mode12h).unwrap();
+    screen.set_pixel(9, 3, 0x0b);
+    assert_eq!((screen.get_pixel(9, 3), screen.get_pixel(8, 3), screen.get_pixel(10, 3)), (0x0b, 0, 0));
+
+    set_text_mode();
+    assert_eq!(graphics_mode(), None);
+    screen.set_pixel(0, 0, 1);
+    let mut after = TextModeState::new();
+    interrupts::without_interrupts(|| save_text_mode(&mut after));
+    assert!(before.font == after.font && before.palette == after.palette);
+    assert_eq!(interrupts::without_interrupts(|| WRITER.lock().read_row(BUFFER_HEIGHT - 1)), row);
+    assert!(interrupts::without_interrupts(|| WRITER.lock().is_active()));
+}
+
+#[test_case]
+fn test_custom_font_and_palette() {
+    use crate::font::DEFAULT_FONT;
+    use x86_64::instructions::interrupts;
+
+    let glyph_at = |col: usize| interrupts::without_interrupts(|| WRITER.lock().read_row(0)[col].ascii_character);
+    let mut bios = TextModeState::new();
+    interrupts::without_interrupts(|| save_text_mode(&mut bios));
+
+    assert!(load_font(&DEFAULT_FONT));
+    interrupts::without_interrupts(|| WRITER.lock().write_at(0, 0, "ãé─"));
+    assert_eq!((glyph_at(0), glyph_at(1), glyph_at(2)), (0xe3, 0xe9, 0x80));
+    let mut loaded = TextModeState::new();
+    interrupts::without_interrupts(|| read_font_plane(&mut loaded.font));
+    assert_eq!(&loaded.font[0x41 * GLYPH_SLOT_SIZE..][..TEXT_FONT_HEIGHT], &DEFAULT_FONT.glyph(0x41)[..TEXT_FONT_HEIGHT]);
+
+    restore_default_font();
+    assert_eq!((glyph_at(0), glyph_at(1), glyph_at(2)), (b'a', 0x82, 0xc4));                        // O texto escrito com a fonte carregada é convertido
+    interrupts::without_interrupts(|| WRITER.lock().write_at(0, 0, "é"));
+    assert_eq!(glyph_at(0), 0x82);
+
+    let other = |f: &dyn Fn(&mut Writer)| interrupts::without_interrupts(|| f(&mut CONSOLES[1].lock()));
+    other(&|writer| writer.write_at(0, 0, "é"));
+    assert!(load_font(&DEFAULT_FONT));
+    other(&|writer| assert_eq!(writer.read_row(0)[0].ascii_character, 0xe9));                       // Inclusive nos consoles que não estão na tela
+    restore_default_font();
+    other(&|writer| assert_eq!(writer.read_row(0)[0].ascii_character, 0x82));
+    interrupts::without_interrupts(|| read_font_plane(&mut loaded.font));
+    assert!(loaded.font == bios.font);
+
+    let brown = color_rgb(Color::Brown);
+    set_color_rgb(Color::Brown, 0xff00ff);
+    assert_eq!(color_rgb(Color::Brown), 0xff00ff);
+    assert_eq!(color_rgb(Color::Red), 0xaa0000);
+    restore_default_palette();
+    assert_eq!(color_rgb(Color::Brown), brown);
+}
+
+#[test_case]
+fn test_regions_and_panes() {
+    use x86_64::instructions::interrupts;
+
+    let text = |row: usize| interrupts::without_interrupts(|| WRITER.lock().read_row(row).map(|cell| cell.ascii_character));
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        assert!(!writer.set_region(Region::new(20, 6)));
+        assert!(writer.set_region(Region::new(0, BUFFER_HEIGHT - 3)));
+        assert_eq!(writer.position().0, BUFFER_HEIGHT - 4);
+        writer.write_string("\nregiao");
+    });
+
+    let mut pane = Pane::new(0, Region::new(BUFFER_HEIGHT - 3, 3)).unwrap();
+    pane.clear();
+    pane.write_string("a\nb\nc\nd");                                                                // A quarta linha rola apenas as linhas da pane
+    assert_eq!((text(BUFFER_HEIGHT - 3)[0], text(BUFFER_HEIGHT - 1)[0]), (b'b', b'd'));
+
+    interrupts::without_interrupts(|| WRITER.lock().write_string("\nx"));
+    assert_eq!(&text(BUFFER_HEIGHT - 5)[..6], b"regiao");
+    assert_eq!((text(BUFFER_HEIGHT - 4)[0], text(BUFFER_HEIGHT - 3)[0]), (b'x', b'b'));
+
+    let mut line = Pane::new(0, Region::new(0, 1)).unwrap();                                        // Mesma geometria da linha de status no topo
+    line.write_string("a\nb");
+    assert_eq!(text(0)[0], b'b');
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        writer.set_region(Region::FULL_SCREEN);
+        writer.set_position(BUFFER_HEIGHT - 1, 0);
+    });
+}
 
 /*pub fn print_something() {
     let mut writer = Writer {
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use rust_os::{exit_qemu, QemuExitCode, serial_print, serial_println};
+use x86_64::VirtAddr;
+use x86_64::structures::idt::PageFaultErrorCode;
+
+const UNMAPPED_ADDRESS: u64 = 0xdeadbeaf000;
+
+/* Esse teste escreve em um endereço não mapeado e espera que o manipulador de page fault do kernel
+* entregue ao resolvedor registrado o endereço lido do CR2 e o código de erro decodificado.
+*/
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("page_fault::page_fault_resolver...\t");
+
+    rust_os::init(boot_info);
+    rust_os::interrupts::set_page_fault_resolver(test_resolver);
+
+    unsafe {
+        *(UNMAPPED_ADDRESS as *mut u8) = 42;
+    }
+
+    panic!("A escrita em um endereço não mapeado não gerou page fault");
+}
+
+fn test_resolver(address: VirtAddr, error_code: PageFaultErrorCode) -> bool {
+    if address == VirtAddr::new(UNMAPPED_ADDRESS)
+        && error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE)
+        && !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION)
+    {
+        serial_println!("[ok]");
+        exit_qemu(QemuExitCode::Success);
+    } else {
+        serial_println!("[failed]\n");
+        serial_println!("Error: endereço {:?}, código de erro {:?}\n", address, error_code);
+        exit_qemu(QemuExitCode::Failed);
+    }
+    false
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+#![feature(abi_x86_interrupt)]
+
+use core::panic::PanicInfo;
+use lazy_static::lazy_static;
+use rust_os::{exit_qemu, QemuExitCode, serial_print, serial_println};
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+
+/* Esse teste provoca um estouro de pilha através de recursão infinita e espera que o double fault
+* seja capturado pelo manipulador executando na pilha da IST, em vez de gerar um triple fault que
+* reiniciaria o qemu.
+*/
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    serial_print!("stack_overflow::stack_overflow...\t");
+
+    rust_os::gdt::init();
+    init_test_idt();
+
+    stack_overflow();
+
+    panic!("A execução continuou após o estouro de pilha");
+}
+
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+    stack_overflow();                                                                               // Cada recursão empilha o endereço de retorno
+    volatile::Volatile::new(0).read();                                                              // Impede otimizações de chamada de cauda
+}
+
+/* Utilizamos uma IDT própria, pois o manipulador de double fault do kernel entra em panic e nós
+* queremos finalizar o qemu com sucesso.
+*/
+lazy_static! {
+    static ref TEST_IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(test_double_fault_handler)
+                .set_stack_index(rust_os::gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt
+    };
+}
+
+pub fn init_test_idt() {
+    TEST_IDT.load();
+}
+
+extern "x86-interrupt" fn test_double_fault_handler(_stack_frame: InterruptStackFrame, _error_code: u64) -> ! {
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}